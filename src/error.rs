@@ -0,0 +1,33 @@
+use std::error;
+use std::fmt;
+
+/// The errors reported by `Interpreter`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// The source did not parse; one message per parse error.
+    Parse(Vec<String>),
+    /// The source ended in the middle of a statement, e.g. inside an
+    /// unclosed `{`; more input may complete it.
+    Incomplete(Vec<String>),
+    /// Evaluation failed, e.g. on a type mismatch or an index out of range.
+    Runtime(String),
+    /// A source file could not be read.
+    Io(String),
+    /// An `Object` could not be converted to the requested Rust type.
+    Conversion(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Parse(errors) | Error::Incomplete(errors) => {
+                write!(f, "Parse error: {}", errors.join("; "))
+            }
+            Error::Runtime(msg) => write!(f, "Runtime error: {}", msg),
+            Error::Io(msg) => write!(f, "IO error: {}", msg),
+            Error::Conversion(msg) => write!(f, "Conversion error: {}", msg),
+        }
+    }
+}
+
+impl error::Error for Error {}
@@ -1,3 +1,13 @@
+pub mod assertions;
 pub mod codegen;
+pub mod convert;
 pub mod env;
 pub mod evaluate;
+pub mod files;
+pub mod format;
+pub mod input;
+pub mod linalg;
+pub mod native;
+pub mod random;
+pub mod stats;
+pub mod tensor;
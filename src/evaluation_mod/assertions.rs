@@ -0,0 +1,71 @@
+use crate::evaluation_mod::evaluate::Object;
+use crate::evaluation_mod::stats::number_value;
+use crate::evaluation_mod::tensor;
+use crate::lexer_mod::token::Span;
+
+pub const ASSERTIONS: [&str; 3] = ["assert", "assert_eq", "assert_matrix_close"];
+
+fn fail(span: Span, msg: String) -> ! {
+    panic!("Assertion failed at {}: {}", span, msg)
+}
+
+/// The shape and row-major elements of a matrix, tensor or list.
+fn numeric_shape(name: &str, object: &Object) -> (Vec<usize>, Vec<Object>) {
+    match object {
+        Object::Array(els) => tensor::from_matrix(els),
+        Object::Tensor(shape, data) => (shape.clone(), data.clone()),
+        Object::List(els) => (vec![els.len()], els.clone()),
+        other => panic!("{} expects matrices, got {:?}", name, other),
+    }
+}
+
+/// `assert(cond)` and `assert(cond, msg)`. `truthy` is `cond` already
+/// evaluated the way an `if` condition would be.
+pub fn assert(args: &[Object], truthy: bool, span: Span) -> Object {
+    if !truthy {
+        match args {
+            [_, msg] => fail(span, msg.to_string()),
+            _ => fail(span, format!("{} is not true", args[0].repr())),
+        }
+    }
+    Object::Null
+}
+
+pub fn assert_eq(lhs: &Object, rhs: &Object, span: Span) -> Object {
+    if lhs != rhs {
+        fail(span, format!("{} != {}", lhs.repr(), rhs.repr()));
+    }
+    Object::Null
+}
+
+/// Checks that two matrices have the same shape and that their elements
+/// differ by at most `eps`.
+pub fn assert_matrix_close(lhs: &Object, rhs: &Object, eps: &Object, span: Span) -> Object {
+    let name = "assert_matrix_close";
+    let eps = number_value(eps);
+    let (lhs_shape, lhs_data) = numeric_shape(name, lhs);
+    let (rhs_shape, rhs_data) = numeric_shape(name, rhs);
+    if lhs_shape != rhs_shape {
+        fail(
+            span,
+            format!("shapes {:?} and {:?} differ", lhs_shape, rhs_shape),
+        );
+    }
+    for (i, (l, r)) in lhs_data.iter().zip(&rhs_data).enumerate() {
+        // Asked this way round so that NaN elements are never close.
+        let close = (number_value(l) - number_value(r)).abs() <= eps;
+        if !close {
+            fail(
+                span,
+                format!(
+                    "elements at position {} differ by more than {}: {} and {}",
+                    i,
+                    Object::Float(eps),
+                    l,
+                    r
+                ),
+            );
+        }
+    }
+    Object::Null
+}
@@ -13,6 +13,12 @@ pub enum Object {
     Print,
 }
 
+/// Compiles `input` to LLVM IR and writes it to `out.ll`.
+///
+/// # Safety
+///
+/// The program must have been parsed with `Parser::for_llvm_backend`, since
+/// this assumes the constructs it rejects never reach codegen.
 pub unsafe fn generate_code(input: Vec<ParseItem::Statement>) {
     let context = llvm::core::LLVMContextCreate();
     let module = llvm::core::LLVMModuleCreateWithName(b"example_module\0".as_ptr() as *const _);
@@ -27,6 +33,8 @@ pub unsafe fn generate_code(input: Vec<ParseItem::Statement>) {
     let mut names = HashMap::new();
     let mut built_ins: HashMap<&'static str, *mut llvm::LLVMValue> = HashMap::new();
     built_ins.insert("printf", create_printf(module));
+    built_ins.insert("puts", create_puts(module));
+    built_ins.insert("abort", create_abort(module));
     codegen(input, module, context, builder, function, names, built_ins);
 }
 
@@ -84,27 +92,36 @@ unsafe fn insert_allocations(
     }
 }
 
-pub fn call_function(
+/// Builds a call to `function` with `args` at the position of `builder`.
+///
+/// # Safety
+///
+/// `builder` must be a valid LLVM builder positioned inside a function, and
+/// `function` and `args` valid values of the same context whose types match.
+pub unsafe fn call_function(
     builder: *mut llvm::LLVMBuilder,
     function: *mut llvm::LLVMValue,
     mut args: Vec<*mut llvm::LLVMValue>,
     name: &str,
 ) -> *mut llvm::LLVMValue {
-    unsafe {
-        llvm::core::LLVMBuildCall(
-            builder,
-            function,
-            args.as_mut_ptr(),
-            args.len() as u32,
-            CString::new(name).unwrap().as_ptr(),
-        )
-    }
+    llvm::core::LLVMBuildCall(
+        builder,
+        function,
+        args.as_mut_ptr(),
+        args.len() as u32,
+        CString::new(name).unwrap().as_ptr(),
+    )
 }
 
 pub fn pointer_type() -> *mut llvm::LLVMType {
     unsafe { llvm::core::LLVMPointerType(llvm::core::LLVMInt64Type(), 0) }
 }
 
+/// Declares the C library's `printf` in `module`.
+///
+/// # Safety
+///
+/// `module` must be a valid LLVM module that has not been disposed of.
 pub unsafe fn create_printf(module: *mut llvm::LLVMModule) -> *mut llvm::LLVMValue {
     let mut printf_args_type_list = vec![pointer_type()];
     let printf_type =
@@ -117,6 +134,75 @@ pub unsafe fn create_printf(module: *mut llvm::LLVMModule) -> *mut llvm::LLVMVal
     )
 }
 
+/// Declares the C library's `puts` in `module`.
+///
+/// # Safety
+///
+/// `module` must be a valid LLVM module that has not been disposed of.
+pub unsafe fn create_puts(module: *mut llvm::LLVMModule) -> *mut llvm::LLVMValue {
+    let mut puts_args_type_list = vec![llvm::core::LLVMPointerType(llvm::core::LLVMInt8Type(), 0)];
+    let puts_type = llvm::core::LLVMFunctionType(
+        llvm::core::LLVMInt32Type(),
+        puts_args_type_list.as_mut_ptr(),
+        1,
+        0,
+    );
+
+    llvm::core::LLVMAddFunction(module, CString::new("puts").unwrap().as_ptr(), puts_type)
+}
+
+/// Declares the C library's `abort` in `module`.
+///
+/// # Safety
+///
+/// `module` must be a valid LLVM module that has not been disposed of.
+pub unsafe fn create_abort(module: *mut llvm::LLVMModule) -> *mut llvm::LLVMValue {
+    let abort_type =
+        llvm::core::LLVMFunctionType(llvm::core::LLVMVoidType(), ptr::null_mut(), 0, 0);
+
+    llvm::core::LLVMAddFunction(module, CString::new("abort").unwrap().as_ptr(), abort_type)
+}
+
+/// The compiled counterpart of a runtime error in the interpreter: when
+/// `failed` is true, prints `msg` and aborts. Code built afterwards runs only
+/// when the check passed.
+unsafe fn build_runtime_check(
+    context: LLVMContextRef,
+    builder: LLVMBuilderRef,
+    func: LLVMValueRef,
+    built_ins: &HashMap<&'static str, *mut llvm::LLVMValue>,
+    failed: LLVMValueRef,
+    msg: &str,
+) {
+    let error_name = CString::new("runtime_error").unwrap();
+    let ok_name = CString::new("check_ok").unwrap();
+    let error_block = llvm::core::LLVMAppendBasicBlockInContext(context, func, error_name.as_ptr());
+    let ok_block = llvm::core::LLVMAppendBasicBlockInContext(context, func, ok_name.as_ptr());
+    llvm::core::LLVMBuildCondBr(builder, failed, error_block, ok_block);
+
+    llvm::core::LLVMPositionBuilderAtEnd(builder, error_block);
+    let text = CString::new(msg).unwrap();
+    let text_name = CString::new("errmsg").unwrap();
+    let text_ptr = llvm::core::LLVMBuildGlobalStringPtr(builder, text.as_ptr(), text_name.as_ptr());
+    call_function(builder, built_ins["puts"], vec![text_ptr], "");
+    call_function(builder, built_ins["abort"], vec![], "");
+    llvm::core::LLVMBuildUnreachable(builder);
+
+    llvm::core::LLVMPositionBuilderAtEnd(builder, ok_block);
+}
+
+/// The target of `op=`, `++` or `--`. `Parser::for_llvm_backend` already
+/// reports any other target as a parse error.
+fn variable_target(target: Expression) -> Expression {
+    match target {
+        Expression::Identifier(_) => target,
+        other => panic!(
+            "The LLVM backend can only update variables, not {:?}",
+            other
+        ),
+    }
+}
+
 unsafe fn codegen_expr(
     context: LLVMContextRef,
     builder: LLVMBuilderRef,
@@ -132,6 +218,11 @@ unsafe fn codegen_expr(
             res
         }
 
+        Statement::Expression(Expression::Bool(value)) => {
+            let int_type = llvm::core::LLVMInt64TypeInContext(context);
+            llvm::core::LLVMConstInt(int_type, value as u64, 0)
+        }
+
         Statement::Expression(Expression::Infix(Infix::Plus, lhs, rhs)) => {
             let lhs = codegen_expr(
                 context,
@@ -205,7 +296,7 @@ unsafe fn codegen_expr(
             );
             let ident_name = match *name {
                 Expression::Identifier(arg) => arg,
-                _ => "".to_string(),
+                _ => panic!("The LLVM backend can only assign to variables"),
             };
             let pointer = names.get(&ident_name).unwrap();
             llvm::core::LLVMBuildStore(builder, new_value, *pointer);
@@ -265,6 +356,418 @@ unsafe fn codegen_expr(
             res
         }
 
+        Statement::Expression(Expression::Infix(Infix::Modulo, lhs, rhs)) => {
+            let lhs = codegen_expr(
+                context,
+                builder,
+                func,
+                names,
+                Statement::Expression(*lhs),
+                built_ins,
+            );
+            let rhs = codegen_expr(
+                context,
+                builder,
+                func,
+                names,
+                Statement::Expression(*rhs),
+                built_ins,
+            );
+
+            let name = CString::new("remtmp").unwrap();
+            let res = llvm::core::LLVMBuildSRem(builder, lhs, rhs, name.as_ptr());
+            call_function(builder, built_ins["printf"], vec![res], "");
+            res
+        }
+
+        Statement::Expression(Expression::Infix(Infix::BitAnd, lhs, rhs)) => {
+            let lhs = codegen_expr(
+                context,
+                builder,
+                func,
+                names,
+                Statement::Expression(*lhs),
+                built_ins,
+            );
+            let rhs = codegen_expr(
+                context,
+                builder,
+                func,
+                names,
+                Statement::Expression(*rhs),
+                built_ins,
+            );
+
+            let name = CString::new("andtmp").unwrap();
+            let res = llvm::core::LLVMBuildAnd(builder, lhs, rhs, name.as_ptr());
+            call_function(builder, built_ins["printf"], vec![res], "");
+            res
+        }
+
+        Statement::Expression(Expression::Infix(Infix::BitOr, lhs, rhs)) => {
+            let lhs = codegen_expr(
+                context,
+                builder,
+                func,
+                names,
+                Statement::Expression(*lhs),
+                built_ins,
+            );
+            let rhs = codegen_expr(
+                context,
+                builder,
+                func,
+                names,
+                Statement::Expression(*rhs),
+                built_ins,
+            );
+
+            let name = CString::new("ortmp").unwrap();
+            let res = llvm::core::LLVMBuildOr(builder, lhs, rhs, name.as_ptr());
+            call_function(builder, built_ins["printf"], vec![res], "");
+            res
+        }
+
+        Statement::Expression(Expression::Infix(Infix::BitXor, lhs, rhs)) => {
+            let lhs = codegen_expr(
+                context,
+                builder,
+                func,
+                names,
+                Statement::Expression(*lhs),
+                built_ins,
+            );
+            let rhs = codegen_expr(
+                context,
+                builder,
+                func,
+                names,
+                Statement::Expression(*rhs),
+                built_ins,
+            );
+
+            let name = CString::new("xortmp").unwrap();
+            let res = llvm::core::LLVMBuildXor(builder, lhs, rhs, name.as_ptr());
+            call_function(builder, built_ins["printf"], vec![res], "");
+            res
+        }
+
+        Statement::Expression(Expression::Infix(Infix::ShiftLeft, lhs, rhs)) => {
+            let lhs = codegen_expr(
+                context,
+                builder,
+                func,
+                names,
+                Statement::Expression(*lhs),
+                built_ins,
+            );
+            let rhs = codegen_expr(
+                context,
+                builder,
+                func,
+                names,
+                Statement::Expression(*rhs),
+                built_ins,
+            );
+
+            let name = CString::new("shltmp").unwrap();
+            let res = llvm::core::LLVMBuildShl(builder, lhs, rhs, name.as_ptr());
+            call_function(builder, built_ins["printf"], vec![res], "");
+            res
+        }
+
+        Statement::Expression(Expression::Infix(Infix::ShiftRight, lhs, rhs)) => {
+            let lhs = codegen_expr(
+                context,
+                builder,
+                func,
+                names,
+                Statement::Expression(*lhs),
+                built_ins,
+            );
+            let rhs = codegen_expr(
+                context,
+                builder,
+                func,
+                names,
+                Statement::Expression(*rhs),
+                built_ins,
+            );
+
+            let name = CString::new("shrtmp").unwrap();
+            let res = llvm::core::LLVMBuildAShr(builder, lhs, rhs, name.as_ptr());
+            call_function(builder, built_ins["printf"], vec![res], "");
+            res
+        }
+
+        Statement::Expression(Expression::Infix(Infix::Power, lhs, rhs)) => {
+            let base = codegen_expr(
+                context,
+                builder,
+                func,
+                names,
+                Statement::Expression(*lhs),
+                built_ins,
+            );
+            let exponent = codegen_expr(
+                context,
+                builder,
+                func,
+                names,
+                Statement::Expression(*rhs),
+                built_ins,
+            );
+            let int_type = llvm::core::LLVMInt64TypeInContext(context);
+            let zero = llvm::core::LLVMConstInt(int_type, 0, 0);
+            let one = llvm::core::LLVMConstInt(int_type, 1, 0);
+
+            // Negative exponents are an error, as in the interpreter: a
+            // constant one is rejected here, any other when the program runs.
+            if !llvm::core::LLVMIsAConstantInt(exponent).is_null() {
+                let value = llvm::core::LLVMConstIntGetSExtValue(exponent);
+                if value < 0 {
+                    panic!("Exponent out of range: {}", value);
+                }
+            } else {
+                let negative_name = CString::new("negexp").unwrap();
+                let negative = llvm::core::LLVMBuildICmp(
+                    builder,
+                    llvm::LLVMIntPredicate::LLVMIntSLT,
+                    exponent,
+                    zero,
+                    negative_name.as_ptr(),
+                );
+                build_runtime_check(
+                    context,
+                    builder,
+                    func,
+                    built_ins,
+                    negative,
+                    "Exponent out of range",
+                );
+            }
+
+            // There is no integer power instruction, so multiply `base` into
+            // an accumulator `exponent` times.
+            let pre_block = llvm::core::LLVMGetInsertBlock(builder);
+            let loop_name = CString::new("powloop").unwrap();
+            let body_name = CString::new("powbody").unwrap();
+            let done_name = CString::new("powdone").unwrap();
+            let loop_block =
+                llvm::core::LLVMAppendBasicBlockInContext(context, func, loop_name.as_ptr());
+            let body_block =
+                llvm::core::LLVMAppendBasicBlockInContext(context, func, body_name.as_ptr());
+            let done_block =
+                llvm::core::LLVMAppendBasicBlockInContext(context, func, done_name.as_ptr());
+            llvm::core::LLVMBuildBr(builder, loop_block);
+
+            llvm::core::LLVMPositionBuilderAtEnd(builder, loop_block);
+            let acc_name = CString::new("powacc").unwrap();
+            let acc = llvm::core::LLVMBuildPhi(builder, int_type, acc_name.as_ptr());
+            let counter_name = CString::new("powcounter").unwrap();
+            let counter = llvm::core::LLVMBuildPhi(builder, int_type, counter_name.as_ptr());
+            let cond_name = CString::new("powcond").unwrap();
+            let cond = llvm::core::LLVMBuildICmp(
+                builder,
+                llvm::LLVMIntPredicate::LLVMIntSGT,
+                counter,
+                zero,
+                cond_name.as_ptr(),
+            );
+            llvm::core::LLVMBuildCondBr(builder, cond, body_block, done_block);
+
+            llvm::core::LLVMPositionBuilderAtEnd(builder, body_block);
+            let next_acc_name = CString::new("powmul").unwrap();
+            let next_acc = llvm::core::LLVMBuildMul(builder, acc, base, next_acc_name.as_ptr());
+            let next_counter_name = CString::new("powdec").unwrap();
+            let next_counter =
+                llvm::core::LLVMBuildSub(builder, counter, one, next_counter_name.as_ptr());
+            llvm::core::LLVMBuildBr(builder, loop_block);
+
+            let mut acc_values = vec![one, next_acc];
+            let mut counter_values = vec![exponent, next_counter];
+            let mut blocks = vec![pre_block, body_block];
+            llvm::core::LLVMAddIncoming(acc, acc_values.as_mut_ptr(), blocks.as_mut_ptr(), 2);
+            llvm::core::LLVMAddIncoming(
+                counter,
+                counter_values.as_mut_ptr(),
+                blocks.as_mut_ptr(),
+                2,
+            );
+
+            llvm::core::LLVMPositionBuilderAtEnd(builder, done_block);
+            call_function(builder, built_ins["printf"], vec![acc], "");
+            acc
+        }
+
+        // The backend has no matrix values, only 64-bit integer variables, so
+        // updates of matrix cells such as `m[i, j] += 1` are rejected when
+        // parsing with `Parser::for_llvm_backend` and left to the interpreter.
+        Statement::Expression(Expression::CompoundAssign(infix, target, value)) => {
+            let update = Expression::compound_assign(infix, variable_target(*target), *value);
+            codegen_expr(
+                context,
+                builder,
+                func,
+                names,
+                Statement::Expression(update),
+                built_ins,
+            )
+        }
+
+        Statement::Expression(Expression::Postfix(postfix, target)) => {
+            let target = Box::new(variable_target(*target));
+            let old_value = codegen_expr(
+                context,
+                builder,
+                func,
+                names,
+                Statement::Expression(*target.clone()),
+                built_ins,
+            );
+            let update =
+                Expression::compound_assign(postfix.infix(), *target, Expression::Integer(1));
+            codegen_expr(
+                context,
+                builder,
+                func,
+                names,
+                Statement::Expression(update),
+                built_ins,
+            );
+            old_value
+        }
+
+        Statement::Expression(Expression::Prefix(Prefix::Plus, expr)) => codegen_expr(
+            context,
+            builder,
+            func,
+            names,
+            Statement::Expression(*expr),
+            built_ins,
+        ),
+
+        Statement::Expression(Expression::Prefix(Prefix::Minus, expr)) => {
+            let value = codegen_expr(
+                context,
+                builder,
+                func,
+                names,
+                Statement::Expression(*expr),
+                built_ins,
+            );
+
+            let name = CString::new("negtmp").unwrap();
+            llvm::core::LLVMBuildNeg(builder, value, name.as_ptr())
+        }
+
+        Statement::Expression(Expression::Prefix(Prefix::BitNot, expr)) => {
+            let value = codegen_expr(
+                context,
+                builder,
+                func,
+                names,
+                Statement::Expression(*expr),
+                built_ins,
+            );
+
+            let name = CString::new("nottmp").unwrap();
+            let res = llvm::core::LLVMBuildNot(builder, value, name.as_ptr());
+            call_function(builder, built_ins["printf"], vec![res], "");
+            res
+        }
+
+        Statement::Expression(Expression::Infix(Infix::Equal, lhs, rhs)) => codegen_comparison(
+            CodegenContext {
+                context,
+                builder,
+                func,
+                names,
+                built_ins,
+            },
+            llvm::LLVMIntPredicate::LLVMIntEQ,
+            *lhs,
+            *rhs,
+        ),
+
+        Statement::Expression(Expression::Infix(Infix::NotEqual, lhs, rhs)) => codegen_comparison(
+            CodegenContext {
+                context,
+                builder,
+                func,
+                names,
+                built_ins,
+            },
+            llvm::LLVMIntPredicate::LLVMIntNE,
+            *lhs,
+            *rhs,
+        ),
+
+        Statement::Expression(Expression::Infix(Infix::LessThan, lhs, rhs)) => codegen_comparison(
+            CodegenContext {
+                context,
+                builder,
+                func,
+                names,
+                built_ins,
+            },
+            llvm::LLVMIntPredicate::LLVMIntSLT,
+            *lhs,
+            *rhs,
+        ),
+
+        Statement::Expression(Expression::Infix(Infix::LessThanAndEqual, lhs, rhs)) => {
+            codegen_comparison(
+                CodegenContext {
+                    context,
+                    builder,
+                    func,
+                    names,
+                    built_ins,
+                },
+                llvm::LLVMIntPredicate::LLVMIntSLE,
+                *lhs,
+                *rhs,
+            )
+        }
+
+        Statement::Expression(Expression::Infix(Infix::MoreThan, lhs, rhs)) => codegen_comparison(
+            CodegenContext {
+                context,
+                builder,
+                func,
+                names,
+                built_ins,
+            },
+            llvm::LLVMIntPredicate::LLVMIntSGT,
+            *lhs,
+            *rhs,
+        ),
+
+        Statement::Expression(Expression::Infix(Infix::MoreThanAndEqual, lhs, rhs)) => {
+            codegen_comparison(
+                CodegenContext {
+                    context,
+                    builder,
+                    func,
+                    names,
+                    built_ins,
+                },
+                llvm::LLVMIntPredicate::LLVMIntSGE,
+                *lhs,
+                *rhs,
+            )
+        }
+
+        Statement::Expression(Expression::Block(stmts)) => {
+            let int_type = llvm::core::LLVMInt64TypeInContext(context);
+            let mut block_return = llvm::core::LLVMConstInt(int_type, 0, 0);
+            for stmt in stmts {
+                block_return = codegen_expr(context, builder, func, names, stmt, built_ins);
+            }
+            block_return
+        }
+
         Statement::Expression(Expression::If(condition, then_body, else_body)) => {
             let condition_value = codegen_expr(
                 context,
@@ -331,3 +834,52 @@ unsafe fn codegen_expr(
         _ => llvm::core::LLVMConstInt(llvm::core::LLVMInt64TypeInContext(context), 0, 0),
     }
 }
+
+/// What lowering an expression needs besides the expression itself.
+struct CodegenContext<'a> {
+    context: LLVMContextRef,
+    builder: LLVMBuilderRef,
+    func: LLVMValueRef,
+    names: &'a mut HashMap<String, LLVMValueRef>,
+    built_ins: &'a HashMap<&'static str, *mut llvm::LLVMValue>,
+}
+
+/// Lowers a comparison to an `icmp` widened back to i64, so that its result
+/// can be stored in variables and used as an `if` condition like any other
+/// value.
+unsafe fn codegen_comparison(
+    codegen: CodegenContext,
+    predicate: llvm::LLVMIntPredicate,
+    lhs: Expression,
+    rhs: Expression,
+) -> LLVMValueRef {
+    let CodegenContext {
+        context,
+        builder,
+        func,
+        names,
+        built_ins,
+    } = codegen;
+    let lhs = codegen_expr(
+        context,
+        builder,
+        func,
+        names,
+        Statement::Expression(lhs),
+        built_ins,
+    );
+    let rhs = codegen_expr(
+        context,
+        builder,
+        func,
+        names,
+        Statement::Expression(rhs),
+        built_ins,
+    );
+
+    let name = CString::new("cmptmp").unwrap();
+    let cmp = llvm::core::LLVMBuildICmp(builder, predicate, lhs, rhs, name.as_ptr());
+    let int_type = llvm::core::LLVMInt64TypeInContext(context);
+    let name = CString::new("booltmp").unwrap();
+    llvm::core::LLVMBuildZExt(builder, cmp, int_type, name.as_ptr())
+}
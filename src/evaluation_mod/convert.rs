@@ -0,0 +1,391 @@
+use crate::error::Error;
+use crate::evaluation_mod::evaluate::{MapKey, Object};
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+
+/// Rust types whose `Vec` converts to a rakiu list. `Vec` itself is not one:
+/// a `Vec<Vec<T>>` converts to a matrix instead, with `Object::try_from`.
+pub trait Element: Into<Object> {}
+
+impl Element for Object {}
+impl Element for i64 {}
+impl Element for f64 {}
+impl Element for bool {}
+impl Element for String {}
+impl<T: Into<Object>> Element for HashMap<String, T> {}
+impl<A: Into<Object>, B: Into<Object>> Element for (A, B) {}
+impl<A: Into<Object>, B: Into<Object>, C: Into<Object>> Element for (A, B, C) {}
+
+fn conversion_error<T>(expected: &str, found: &Object) -> Result<T, Error> {
+    Err(Error::Conversion(format!(
+        "expected {}, got {:?}",
+        expected, found
+    )))
+}
+
+impl From<i64> for Object {
+    fn from(num: i64) -> Self {
+        Object::Integer(num)
+    }
+}
+
+impl From<f64> for Object {
+    fn from(num: f64) -> Self {
+        Object::Float(num)
+    }
+}
+
+impl From<bool> for Object {
+    fn from(_bool: bool) -> Self {
+        Object::Boolean(_bool)
+    }
+}
+
+impl From<String> for Object {
+    fn from(string: String) -> Self {
+        Object::String(string)
+    }
+}
+
+impl From<&str> for Object {
+    fn from(string: &str) -> Self {
+        Object::String(string.to_string())
+    }
+}
+
+impl<T: Element> From<Vec<T>> for Object {
+    fn from(els: Vec<T>) -> Self {
+        Object::List(els.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Fails if the rows are not all the same length, since a matrix is
+/// rectangular.
+impl<T: Element> TryFrom<Vec<Vec<T>>> for Object {
+    type Error = Error;
+
+    fn try_from(rows: Vec<Vec<T>>) -> Result<Self, Error> {
+        if let Some(row) = rows.iter().find(|row| row.len() != rows[0].len()) {
+            return Err(Error::Conversion(format!(
+                "matrix rows must have the same length, got {} and {}",
+                rows[0].len(),
+                row.len()
+            )));
+        }
+        Ok(Object::Array(
+            rows.into_iter()
+                .map(|row| row.into_iter().map(Into::into).collect())
+                .collect(),
+        ))
+    }
+}
+
+impl<T: Into<Object>> From<HashMap<String, T>> for Object {
+    fn from(map: HashMap<String, T>) -> Self {
+        Object::Map(
+            map.into_iter()
+                .map(|(key, value)| (MapKey::String(key), value.into()))
+                .collect::<BTreeMap<_, _>>(),
+        )
+    }
+}
+
+impl<A: Into<Object>, B: Into<Object>> From<(A, B)> for Object {
+    fn from((a, b): (A, B)) -> Self {
+        Object::List(vec![a.into(), b.into()])
+    }
+}
+
+impl<A: Into<Object>, B: Into<Object>, C: Into<Object>> From<(A, B, C)> for Object {
+    fn from((a, b, c): (A, B, C)) -> Self {
+        Object::List(vec![a.into(), b.into(), c.into()])
+    }
+}
+
+impl TryFrom<Object> for i64 {
+    type Error = Error;
+
+    fn try_from(object: Object) -> Result<Self, Error> {
+        match object {
+            Object::Integer(num) => Ok(num),
+            other => conversion_error("an integer", &other),
+        }
+    }
+}
+
+/// Integers are accepted as well.
+impl TryFrom<Object> for f64 {
+    type Error = Error;
+
+    fn try_from(object: Object) -> Result<Self, Error> {
+        match object {
+            Object::Float(num) => Ok(num),
+            Object::Integer(num) => Ok(num as f64),
+            other => conversion_error("a number", &other),
+        }
+    }
+}
+
+impl TryFrom<Object> for bool {
+    type Error = Error;
+
+    fn try_from(object: Object) -> Result<Self, Error> {
+        match object {
+            Object::Boolean(_bool) => Ok(_bool),
+            other => conversion_error("a boolean", &other),
+        }
+    }
+}
+
+impl TryFrom<Object> for String {
+    type Error = Error;
+
+    fn try_from(object: Object) -> Result<Self, Error> {
+        match object {
+            Object::String(string) => Ok(string),
+            other => conversion_error("a string", &other),
+        }
+    }
+}
+
+/// Converts a list element by element. A matrix converts row by row, each
+/// row as a list, so `Vec<Vec<T>>` accepts matrices as well as nested lists.
+impl<T: TryFrom<Object, Error = Error>> TryFrom<Object> for Vec<T> {
+    type Error = Error;
+
+    fn try_from(object: Object) -> Result<Self, Error> {
+        match object {
+            Object::List(els) => els.into_iter().map(T::try_from).collect(),
+            Object::Array(rows) => rows
+                .into_iter()
+                .map(|row| T::try_from(Object::List(row)))
+                .collect(),
+            Object::Tensor(shape, data) if shape.len() == 1 => {
+                data.into_iter().map(T::try_from).collect()
+            }
+            other => conversion_error("a list", &other),
+        }
+    }
+}
+
+impl<T: TryFrom<Object, Error = Error>> TryFrom<Object> for HashMap<String, T> {
+    type Error = Error;
+
+    fn try_from(object: Object) -> Result<Self, Error> {
+        match object {
+            Object::Map(map) => map
+                .into_iter()
+                .map(|(key, value)| match key {
+                    MapKey::String(key) => Ok((key, T::try_from(value)?)),
+                    other => conversion_error("string keys", &other.to_object()),
+                })
+                .collect(),
+            other => conversion_error("a map", &other),
+        }
+    }
+}
+
+fn list_of_length(object: Object, len: usize) -> Result<Vec<Object>, Error> {
+    match object {
+        Object::List(els) if els.len() == len => Ok(els),
+        other => conversion_error(&format!("a list of length {}", len), &other),
+    }
+}
+
+impl<A, B> TryFrom<Object> for (A, B)
+where
+    A: TryFrom<Object, Error = Error>,
+    B: TryFrom<Object, Error = Error>,
+{
+    type Error = Error;
+
+    fn try_from(object: Object) -> Result<Self, Error> {
+        let mut els = list_of_length(object, 2)?.into_iter();
+        Ok((
+            A::try_from(els.next().unwrap())?,
+            B::try_from(els.next().unwrap())?,
+        ))
+    }
+}
+
+impl<A, B, C> TryFrom<Object> for (A, B, C)
+where
+    A: TryFrom<Object, Error = Error>,
+    B: TryFrom<Object, Error = Error>,
+    C: TryFrom<Object, Error = Error>,
+{
+    type Error = Error;
+
+    fn try_from(object: Object) -> Result<Self, Error> {
+        let mut els = list_of_length(object, 3)?.into_iter();
+        Ok((
+            A::try_from(els.next().unwrap())?,
+            B::try_from(els.next().unwrap())?,
+            C::try_from(els.next().unwrap())?,
+        ))
+    }
+}
+
+/// Argument lists that can be unpacked from the arguments of a native
+/// function: tuples of up to four types that convert from `Object`.
+pub trait FromArgs: Sized {
+    const ARITY: usize;
+
+    fn from_args(args: &[Object]) -> Result<Self, Error>;
+}
+
+fn argument<T: TryFrom<Object, Error = Error>>(args: &[Object], index: usize) -> Result<T, Error> {
+    T::try_from(args[index].clone()).map_err(|err| match err {
+        Error::Conversion(msg) => Error::Conversion(format!("argument {}: {}", index + 1, msg)),
+        err => err,
+    })
+}
+
+macro_rules! impl_from_args {
+    ($arity:expr; $($ty:ident $index:tt),*) => {
+        impl<$($ty: TryFrom<Object, Error = Error>),*> FromArgs for ($($ty,)*) {
+            const ARITY: usize = $arity;
+
+            #[allow(unused_variables)]
+            fn from_args(args: &[Object]) -> Result<Self, Error> {
+                if args.len() != $arity {
+                    return Err(Error::Conversion(format!(
+                        "expected {} arguments, got {}",
+                        $arity,
+                        args.len()
+                    )));
+                }
+                Ok(($(argument::<$ty>(args, $index)?,)*))
+            }
+        }
+    };
+}
+
+impl_from_args!(0;);
+impl_from_args!(1; A 0);
+impl_from_args!(2; A 0, B 1);
+impl_from_args!(3; A 0, B 1, C 2);
+impl_from_args!(4; A 0, B 1, C 2, D 3);
+
+/// Unpacks native function arguments into Rust types, e.g.
+/// `let (m, k): (Vec<Vec<i64>>, i64) = unpack(args)?;`.
+pub fn unpack<T: FromArgs>(args: &[Object]) -> Result<T, Error> {
+    T::from_args(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_rust() {
+        assert_eq!(Object::from(3), Object::Integer(3));
+        assert_eq!(Object::from("a"), Object::String("a".to_string()));
+        assert_eq!(
+            Object::from(vec![1, 2]),
+            Object::List(vec![Object::Integer(1), Object::Integer(2)])
+        );
+        assert_eq!(
+            Object::try_from(vec![vec![true], vec![false]]),
+            Ok(Object::Array(vec![
+                vec![Object::Boolean(true)],
+                vec![Object::Boolean(false)]
+            ]))
+        );
+        assert_eq!(
+            Object::from(vec![(1, "a".to_string())]),
+            Object::List(vec![Object::List(vec![
+                Object::Integer(1),
+                Object::String("a".to_string())
+            ])])
+        );
+        let mut map = HashMap::new();
+        map.insert("x".to_string(), 1.5);
+        assert_eq!(
+            Object::from(map),
+            Object::Map(
+                vec![(MapKey::String("x".to_string()), Object::Float(1.5))]
+                    .into_iter()
+                    .collect()
+            )
+        );
+    }
+
+    #[test]
+    fn test_to_rust() {
+        let matrix = Object::try_from(vec![vec![1, 2], vec![3, 4]]).unwrap();
+        assert_eq!(
+            Vec::<Vec<i64>>::try_from(matrix.clone()),
+            Ok(vec![vec![1, 2], vec![3, 4]])
+        );
+        assert_eq!(
+            Vec::<Vec<f64>>::try_from(matrix),
+            Ok(vec![vec![1.0, 2.0], vec![3.0, 4.0]])
+        );
+        assert_eq!(
+            Vec::<String>::try_from(Object::from(vec!["a".to_string()])),
+            Ok(vec!["a".to_string()])
+        );
+        assert_eq!(
+            <(i64, bool)>::try_from(Object::from((1, true))),
+            Ok((1, true))
+        );
+        let mut map = HashMap::new();
+        map.insert("n".to_string(), vec![1, 2]);
+        assert_eq!(
+            HashMap::<String, Vec<i64>>::try_from(Object::from(map.clone())),
+            Ok(map)
+        );
+        assert_eq!(
+            Vec::<i64>::try_from(Object::from(vec![
+                Object::Integer(1),
+                Object::Boolean(true)
+            ])),
+            Err(Error::Conversion(
+                "expected an integer, got Boolean(true)".to_string()
+            ))
+        );
+        assert!(<(i64, i64)>::try_from(Object::from(vec![1, 2, 3])).is_err());
+        assert_eq!(
+            Vec::<i64>::try_from(Object::Tensor(
+                vec![2],
+                vec![Object::Integer(1), Object::Integer(2)]
+            )),
+            Ok(vec![1, 2])
+        );
+        assert!(
+            Vec::<i64>::try_from(Object::Tensor(vec![1, 1], vec![Object::Integer(1)])).is_err()
+        );
+    }
+
+    #[test]
+    fn test_ragged_rows() {
+        assert_eq!(
+            Object::try_from(vec![vec![1, 2], vec![3]]),
+            Err(Error::Conversion(
+                "matrix rows must have the same length, got 2 and 1".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_unpack() {
+        let args = vec![
+            Object::try_from(vec![vec![1, 2]]).unwrap(),
+            Object::Integer(3),
+        ];
+        let (m, k): (Vec<Vec<i64>>, i64) = unpack(&args).unwrap();
+        assert_eq!((m, k), (vec![vec![1, 2]], 3));
+        assert_eq!(
+            unpack::<(i64, String)>(&args),
+            Err(Error::Conversion(
+                "argument 1: expected an integer, got Array([[Integer(1), Integer(2)]])"
+                    .to_string()
+            ))
+        );
+        assert_eq!(
+            unpack::<(i64,)>(&args),
+            Err(Error::Conversion("expected 1 arguments, got 2".to_string()))
+        );
+    }
+}
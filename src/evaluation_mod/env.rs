@@ -1,26 +1,216 @@
 use crate::evaluation_mod::evaluate::Object;
+use crate::evaluation_mod::native::NativeFunction;
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 
 pub struct Env {
     pub env: HashMap<String, Object>,
+    /// Field names of the declared struct types, by struct name.
+    pub structs: HashMap<String, Vec<String>>,
+    /// Reject non-boolean `if` conditions instead of applying truthiness.
+    pub strict: bool,
+    /// Functions registered by the host application, by name.
+    natives: HashMap<String, NativeFunction>,
+    /// Directories the file builtins may access, canonicalized. Empty, and so
+    /// denying all file access, unless the host allows some.
+    allowed_dirs: Vec<PathBuf>,
+    /// Where `input` and the other reading builtins take their lines from;
+    /// standard input unless the host sets another source.
+    input: Option<Rc<RefCell<dyn BufRead>>>,
+    /// One frame per block being evaluated: the variables it bound, each
+    /// with the value it hid, or `None` if it was new. `exit_scope` puts
+    /// these back, so blocks need no copy of the variables.
+    scopes: Vec<Vec<(String, Option<Object>)>>,
+    /// The functions declared at the top level of the program, visible in
+    /// every call scope so that functions can call one another.
+    functions: Rc<HashMap<String, Object>>,
+    /// Whether this is the program's own scope rather than a function call's.
+    top_level: bool,
+}
+
+impl Default for Env {
+    fn default() -> Self {
+        Env::new()
+    }
 }
 
 impl Env {
     pub fn new() -> Self {
         Env {
             env: HashMap::new(),
+            structs: HashMap::new(),
+            strict: false,
+            natives: HashMap::new(),
+            allowed_dirs: vec![],
+            input: None,
+            scopes: vec![],
+            functions: Rc::new(HashMap::new()),
+            top_level: true,
+        }
+    }
+
+    /// An empty scope for a function call, keeping the struct declarations,
+    /// top-level functions, native functions and interpreter settings of the
+    /// caller.
+    pub fn new_scope(&self) -> Self {
+        Env {
+            env: HashMap::new(),
+            structs: self.structs.clone(),
+            strict: self.strict,
+            natives: self.natives.clone(),
+            allowed_dirs: self.allowed_dirs.clone(),
+            input: self.input.clone(),
+            scopes: vec![],
+            functions: Rc::clone(&self.functions),
+            top_level: false,
+        }
+    }
+
+    /// Starts a nested scope for a block. It sees the enclosing variables,
+    /// and assignments to them stay after `exit_scope`.
+    pub fn enter_scope(&mut self) {
+        self.scopes.push(vec![]);
+    }
+
+    /// Ends the innermost scope, dropping the variables bound in it and
+    /// restoring those they hid.
+    pub fn exit_scope(&mut self) {
+        let frame = self.scopes.pop().expect("No scope to exit");
+        for (key, hidden) in frame.into_iter().rev() {
+            match hidden {
+                Some(val) => self.env.insert(key, val),
+                None => self.env.remove(&key),
+            };
+        }
+    }
+
+    /// Ends every open scope, as after a runtime error inside a block.
+    pub fn exit_all_scopes(&mut self) {
+        while !self.scopes.is_empty() {
+            self.exit_scope();
         }
     }
 
+    /// Binds a new variable in the innermost scope, as `let` does.
+    pub fn declare(&mut self, key: String, val: Object) {
+        let hidden = self.env.insert(key.clone(), val);
+        if let Some(frame) = self.scopes.last_mut() {
+            frame.push((key, hidden));
+        }
+    }
+
+    /// Binds a function declared by `fn name(...)`. Outside any block of the
+    /// program's own scope, it also becomes visible inside function calls.
+    pub fn declare_function(&mut self, name: String, function: Object) {
+        if self.top_level && self.scopes.is_empty() {
+            Rc::make_mut(&mut self.functions).insert(name.clone(), function.clone());
+        }
+        self.declare(name, function);
+    }
+
+    /// Makes a native function callable by its name. It takes precedence over
+    /// a builtin of the same name, but not over a user-defined function.
+    pub fn register(&mut self, function: NativeFunction) {
+        self.natives.insert(function.name.clone(), function);
+    }
+
+    pub fn native(&self, name: &str) -> Option<&NativeFunction> {
+        self.natives.get(name)
+    }
+
+    pub fn natives(&self) -> impl Iterator<Item = &NativeFunction> {
+        self.natives.values()
+    }
+
+    /// Lets the file builtins read and write anywhere inside `dir`.
+    pub fn allow_dir<P: AsRef<Path>>(&mut self, dir: P) -> io::Result<()> {
+        let dir = dir.as_ref().canonicalize()?;
+        self.allowed_dirs.push(dir);
+        Ok(())
+    }
+
+    /// Resolves `path` for a file builtin, panicking unless it lies inside an
+    /// allowed directory. Symbolic links and `..` are resolved first, so they
+    /// cannot lead out of one; a file that does not exist yet is checked by
+    /// its parent directory. A dangling symbolic link is refused, since
+    /// writing through it would create its target wherever that is.
+    pub fn check_access(&self, path: &str) -> PathBuf {
+        let target = Path::new(path);
+        let resolved = target.canonicalize().or_else(|_| {
+            if matches!(fs::symlink_metadata(target), Ok(meta) if meta.file_type().is_symlink()) {
+                return Err(io::Error::from(io::ErrorKind::PermissionDenied));
+            }
+            let parent = match target.parent() {
+                Some(parent) if parent != Path::new("") => parent,
+                _ => Path::new("."),
+            };
+            match target.file_name() {
+                Some(name) => parent.canonicalize().map(|parent| parent.join(name)),
+                None => Err(io::Error::from(io::ErrorKind::NotFound)),
+            }
+        });
+        match resolved {
+            Ok(resolved)
+                if self
+                    .allowed_dirs
+                    .iter()
+                    .any(|dir| resolved.starts_with(dir)) =>
+            {
+                resolved
+            }
+            _ => panic!("Access to {} is not allowed", path),
+        }
+    }
+
+    pub fn set_input<R: BufRead + 'static>(&mut self, reader: R) {
+        self.input = Some(Rc::new(RefCell::new(reader)));
+    }
+
+    /// The next line of input without its line ending, or `None` at the end
+    /// of input. Standard input is read through its shared buffer, so lines
+    /// read here are never seen by a REPL reading the same stream.
+    pub fn read_line(&self) -> Option<String> {
+        let mut line = String::new();
+        let read = match &self.input {
+            Some(input) => input.borrow_mut().read_line(&mut line),
+            None => io::stdin().lock().read_line(&mut line),
+        };
+        match read {
+            Ok(0) => None,
+            Ok(_) => {
+                if line.ends_with('\n') {
+                    line.pop();
+                    if line.ends_with('\r') {
+                        line.pop();
+                    }
+                }
+                Some(line)
+            }
+            Err(err) => panic!("Could not read input: {}", err),
+        }
+    }
+
+    /// Assigns to a variable; one that does not exist yet is bound in the
+    /// innermost scope.
     pub fn set(&mut self, key: String, val: Object) {
-        self.env.insert(key, val);
+        if self.env.contains_key(&key) {
+            self.env.insert(key, val);
+        } else {
+            self.declare(key, val);
+        }
     }
 
     pub fn get(&mut self, key: &str) -> Option<Object> {
-        self.env.get(key).map(|val| val.clone())
+        self.get_by_ref(key).cloned()
     }
 
+    /// Looks `key` up among the variables, then the top-level functions.
     pub fn get_by_ref(&mut self, key: &str) -> Option<&Object> {
-        self.env.get(key)
+        let functions = &self.functions;
+        self.env.get(key).or_else(|| functions.get(key))
     }
 }
@@ -1,18 +1,168 @@
+use crate::error::Error;
+use crate::evaluation_mod::assertions::{self, ASSERTIONS};
 use crate::evaluation_mod::env::Env;
+use crate::evaluation_mod::files;
+use crate::evaluation_mod::format;
+use crate::evaluation_mod::input;
+use crate::evaluation_mod::linalg;
+use crate::evaluation_mod::random::Rng;
+use crate::evaluation_mod::stats::{self, number_value};
+use crate::evaluation_mod::tensor;
 use crate::lexer_mod::lexer::Lexer;
-use crate::parser_mod::ParseItem::{Expression, Infix, Prefix, Statement};
+use crate::lexer_mod::token::Span;
+use crate::parser_mod::ParseItem::{Expression, Infix, Pattern, Prefix, Statement};
 use crate::parser_mod::Parser::Parser;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
 use std::io::{self, Write};
 
 #[derive(Clone, PartialEq, Debug)]
 pub enum Object {
     Null,
     Integer(i64),
+    Float(f64),
     String(String),
     Boolean(bool),
     Return(Box<Object>),
     Function(String, Vec<String>, Vec<Statement>),
     Array(Vec<Vec<Object>>),
+    /// A one-dimensional list of any values, including other lists.
+    List(Vec<Object>),
+    Map(BTreeMap<MapKey, Object>),
+    /// A dense N-dimensional tensor: its shape and its elements in row-major
+    /// order.
+    Tensor(Vec<usize>, Vec<Object>),
+    /// A struct instance: its type name and field values in declaration order.
+    Struct(String, Vec<(String, Object)>),
+}
+
+/// Values are shown in the language's own syntax, so that most of them can
+/// be parsed back into an equal value. Strings are quoted only when nested in
+/// another value; tensors, which have no literal, show as nested lists.
+impl fmt::Display for Object {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Object::Null => write!(f, "null"),
+            Object::Integer(num) => write!(f, "{}", num),
+            Object::Float(num) => write!(f, "{}", float_literal(*num)),
+            Object::Boolean(_bool) => write!(f, "{}", _bool),
+            Object::String(string) => write!(f, "{}", string),
+            Object::Return(value) => write!(f, "{}", value),
+            Object::Function(name, params, _) => write!(f, "<fn {}({})>", name, params.join(", ")),
+            Object::Array(els) => {
+                write!(f, "[")?;
+                for (i, row) in els.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{{")?;
+                    write_separated(f, row.iter().map(Object::repr))?;
+                    write!(f, "}}")?;
+                }
+                write!(f, "]")
+            }
+            Object::List(els) => {
+                write!(f, "[")?;
+                write_separated(f, els.iter().map(Object::repr))?;
+                write!(f, "]")
+            }
+            Object::Map(map) => {
+                write!(f, "{{")?;
+                write_separated(
+                    f,
+                    map.iter().map(|(key, value)| {
+                        format!("{}: {}", key.to_object().repr(), value.repr())
+                    }),
+                )?;
+                write!(f, "}}")
+            }
+            Object::Tensor(shape, data) => tensor::write_nested(f, shape, data),
+            Object::Struct(name, fields) => {
+                write!(f, "{} {{ ", name)?;
+                write_separated(
+                    f,
+                    fields
+                        .iter()
+                        .map(|(field, value)| format!("{}: {}", field, value.repr())),
+                )?;
+                write!(f, " }}")
+            }
+        }
+    }
+}
+
+impl Object {
+    /// The value as it appears nested in a list, map or matrix: like its
+    /// `Display` form, except that strings keep their quotes.
+    pub fn repr(&self) -> String {
+        match self {
+            Object::String(string) => {
+                let mut quoted = String::from("\"");
+                for ch in string.chars() {
+                    match ch {
+                        '"' => quoted.push_str("\\\""),
+                        '\\' => quoted.push_str("\\\\"),
+                        '\n' => quoted.push_str("\\n"),
+                        '\t' => quoted.push_str("\\t"),
+                        ch => quoted.push(ch),
+                    }
+                }
+                quoted.push('"');
+                quoted
+            }
+            other => other.to_string(),
+        }
+    }
+}
+
+/// A float as the lexer reads it back: always with a decimal point, and never
+/// in exponent notation.
+fn float_literal(num: f64) -> String {
+    let literal = num.to_string();
+    if num.is_finite() && !literal.contains('.') {
+        format!("{}.0", literal)
+    } else {
+        literal
+    }
+}
+
+fn write_separated<I: Iterator<Item = String>>(f: &mut fmt::Formatter, items: I) -> fmt::Result {
+    for (i, item) in items.enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+
+/// The values that can be used as keys of an `Object::Map`.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum MapKey {
+    Integer(i64),
+    String(String),
+    Boolean(bool),
+}
+
+impl MapKey {
+    pub fn from_object(object: Object) -> MapKey {
+        match object {
+            Object::Integer(num) => MapKey::Integer(num),
+            Object::String(string) => MapKey::String(string),
+            Object::Boolean(_bool) => MapKey::Boolean(_bool),
+            _ => panic!("{:?} cannot be used as a map key", object),
+        }
+    }
+
+    pub fn to_object(&self) -> Object {
+        match self {
+            MapKey::Integer(num) => Object::Integer(*num),
+            MapKey::String(string) => Object::String(string.clone()),
+            MapKey::Boolean(_bool) => Object::Boolean(*_bool),
+        }
+    }
 }
 
 fn eval_expr(expression: Expression, env: &mut Env) -> Object {
@@ -25,416 +175,1219 @@ fn eval_expr(expression: Expression, env: &mut Env) -> Object {
             }
             Object::Array(els)
         }
-        Expression::Index(input_array, first_num, second_num) => {
-            let arr_name;
-            match eval_expr(*input_array, env) {
-                Object::String(name) => arr_name = name,
-                _ => panic!("Invalid identifier"),
-            }
-            let mut array = vec![];
-            match env.get(arr_name.as_str()) {
-                Some(Object::Array(els)) => {
-                    let mut row_arr = vec![];
-                    for row in els {
-                        for col in row {
-                            match col {
-                                Object::Integer(num) => row_arr.push(num),
-                                _ => continue,
-                            }
-                        }
-                    }
-                    array.push(row_arr);
+        Expression::List(elems) => {
+            Object::List(elems.into_iter().map(|expr| eval_expr(expr, env)).collect())
+        }
+        Expression::Index(input_array, indices) => {
+            let container = eval_expr(*input_array, env);
+            let args = eval_indices(indices, env);
+            let indices = match plain_indices(&args) {
+                Some(indices) => indices,
+                None => return select_slice(container, &args),
+            };
+            match (container, indices.as_slice()) {
+                // `m[i]` is row `i`, the same as `m[i:i + 1]`.
+                (container @ Object::Array(_), [_]) => select_slice(container, &args),
+                (Object::Array(els), [first, second]) => {
+                    let (row, col) = cell_position(
+                        &els,
+                        integer_index(first.clone()),
+                        integer_index(second.clone()),
+                    );
+                    els[row][col].clone()
                 }
-                _ => panic!("Unknown array"),
-            }
-
-            match *second_num {
-                Some(expr) => match (eval_expr(*first_num, env), eval_expr(expr, env)) {
-                    (Object::Integer(num1), Object::Integer(num2)) => {
-                        if num1 > array.len() as i64 {
-                            panic!("Array rows out of range");
-                        }
-                        if num2 > array[num1 as usize].len() as i64 {
-                            panic!("Array column out of range");
-                        }
-                        Object::Integer(array[num1 as usize][num2 as usize])
+                (Object::List(els), [first]) => {
+                    let index = list_position(&els, integer_index(first.clone()));
+                    els[index].clone()
+                }
+                (Object::Map(map), [first]) => {
+                    let key = MapKey::from_object(first.clone());
+                    match map.get(&key) {
+                        Some(value) => value.clone(),
+                        None => panic!("Key {:?} not found", key),
                     }
-                    _ => panic!("Invalid index"),
-                },
-                None => match eval_expr(*first_num, env) {
-                    Object::Integer(num1) => {
-                        if num1 > array.len() as i64 {
-                            panic!("Array index out of range");
-                        }
-                        Object::Integer(array[0][num1 as usize])
+                }
+                (Object::Tensor(shape, data), indices) => {
+                    let indices: Vec<i64> = indices.iter().cloned().map(integer_index).collect();
+                    let (range, rest) = tensor::locate(&shape, &indices);
+                    if rest.is_empty() {
+                        data[range.start].clone()
+                    } else {
+                        Object::Tensor(rest, data[range].to_vec())
                     }
-                    _ => panic!("Invalid index"),
-                },
+                }
+                (container, indices) => panic!(
+                    "Cannot index {:?} with {} indices",
+                    container,
+                    indices.len()
+                ),
+            }
+        }
+        Expression::Slice(..) => panic!("Slices can only be used inside an index"),
+        Expression::String(string) => Object::String(string),
+        Expression::Map(pairs) => {
+            let mut map = BTreeMap::new();
+            for (key, value) in pairs {
+                let key = MapKey::from_object(eval_expr(key, env));
+                map.insert(key, eval_expr(value, env));
             }
+            Object::Map(map)
         }
         Expression::Integer(num) => Object::Integer(num),
+        Expression::Float(num) => Object::Float(num),
         Expression::Bool(_bool) => Object::Boolean(_bool),
-        Expression::Identifier(name) => {
-            let value = env
-                .get(&name.as_str())
-                .expect(format!("{} not found", name).as_str());
-            Object::String(name)
-        }
-        Expression::Function(ident, params, body) => Object::Function(ident, params, body),
-        Expression::Call { func, args } => {
-            let (params, body) = match *func {
+        Expression::Identifier(name) => match env.get(&name) {
+            Some(value) => value,
+            None => panic!("{} not found", name),
+        },
+        // Evaluating `fn name(...) { ... }` also binds `name`, so named
+        // functions can be called and passed around like any other value.
+        Expression::Function(ident, params, body) => {
+            let function = Object::Function(ident.clone(), params, body);
+            env.declare_function(ident, function.clone());
+            function
+        }
+        Expression::Call { func, args, span } => {
+            let function = match *func {
                 Expression::Identifier(name) => match env.get(&name) {
-                    Some(Object::Function(_, args, body)) => (args, body),
+                    Some(function @ Object::Function(..)) => function,
                     _ => {
-                        let elems = args.into_iter().map(|expr| eval_expr(expr, env)).collect();
+                        let elems: Vec<Object> =
+                            args.into_iter().map(|expr| eval_expr(expr, env)).collect();
+                        if let Some(native) = env.native(&name) {
+                            return native.clone().call(&elems);
+                        }
+                        if ASSERTIONS.contains(&name.as_str()) {
+                            return eval_assertion(&name, &elems, span, env);
+                        }
                         return eval_builtin(&name, elems, env)
                             .expect(format!("Unrecognized function {:#?}", name).as_str());
                     }
                 },
-                _ => panic!("yo, what's that?"),
+                callee => eval_expr(callee, env),
             };
-
-            if params.len() != args.len() {
-                panic!("Wrong number of params");
-            }
-
-            let mut env2 = Env::new();
-            for (arg, val) in params.into_iter().zip(args.into_iter()) {
-                env2.set(arg, eval_expr(val, env));
-            }
-
-            eval_return(body, &mut env2)
+            let args = args.into_iter().map(|expr| eval_expr(expr, env)).collect();
+            call_function(function, args, env)
         }
         Expression::Prefix(Prefix::Not, expression) => match eval_expr(*expression, env) {
             Object::Boolean(_bool) => Object::Boolean(!_bool),
             _ => panic!("Can use ! operator only for booleans"),
         },
+        Expression::Prefix(Prefix::Plus, expression) => match eval_expr(*expression, env) {
+            Object::Integer(num) => Object::Integer(num),
+            Object::Float(num) => Object::Float(num),
+            _ => panic!("Can use the '+' operator only for numbers"),
+        },
         Expression::Prefix(Prefix::Minus, expression) => match eval_expr(*expression, env) {
             Object::Integer(num) => Object::Integer(-num),
+            Object::Float(num) => Object::Float(-num),
             _ => panic!("Can use the '-' operator only for numbers"),
         },
+        Expression::Prefix(Prefix::BitNot, expression) => match eval_expr(*expression, env) {
+            Object::Integer(num) => Object::Integer(!num),
+            _ => panic!("Can use the '~' operator only for numbers"),
+        },
         Expression::Infix(Infix::Plus, lhs, rhs) => {
+            eval_arithmetic(Infix::Plus, eval_expr(*lhs, env), eval_expr(*rhs, env))
+        }
+        Expression::Infix(Infix::Minus, lhs, rhs) => {
+            eval_arithmetic(Infix::Minus, eval_expr(*lhs, env), eval_expr(*rhs, env))
+        }
+        Expression::Infix(Infix::Divide, lhs, rhs) => {
+            eval_arithmetic(Infix::Divide, eval_expr(*lhs, env), eval_expr(*rhs, env))
+        }
+        Expression::Infix(Infix::Multiply, lhs, rhs) => {
+            eval_arithmetic(Infix::Multiply, eval_expr(*lhs, env), eval_expr(*rhs, env))
+        }
+        Expression::Infix(Infix::MatMul, lhs, rhs) => {
             match (eval_expr(*lhs, env), eval_expr(*rhs, env)) {
-                (Object::Integer(num1), Object::Integer(num2)) => Object::Integer(num1 + num2),
-                _ => panic!("Can only add integer literals"),
+                (Object::Array(lhs), Object::Array(rhs)) => Object::Array(matmul(&lhs, &rhs)),
+                (lhs, rhs) if is_elementwise_operand(&lhs) && is_elementwise_operand(&rhs) => {
+                    let product = matmul(&matmul_operand(lhs), &matmul_operand(rhs));
+                    let (shape, data) = tensor::from_matrix(&product);
+                    Object::Tensor(shape, data)
+                }
+                _ => panic!("Can only use the '@' operator for matrices"),
             }
         }
-        Expression::Infix(Infix::Minus, lhs, rhs) => {
+        Expression::Infix(Infix::Modulo, lhs, rhs) => {
             match (eval_expr(*lhs, env), eval_expr(*rhs, env)) {
-                (Object::Integer(num1), Object::Integer(num2)) => Object::Integer(num1 - num2),
-                _ => panic!("Can only subtract integer literals"),
+                (Object::Integer(_), Object::Integer(0)) => panic!("Modulo by zero"),
+                (Object::Integer(num1), Object::Integer(num2)) => match num1.checked_rem(num2) {
+                    Some(num) => Object::Integer(num),
+                    None => panic!("Integer overflow in {} % {}", num1, num2),
+                },
+                _ => panic!("Can only take the modulo of integer literals"),
             }
         }
-        Expression::Infix(Infix::Divide, lhs, rhs) => {
+        Expression::Infix(Infix::Power, lhs, rhs) => {
+            match (eval_expr(*lhs, env), eval_expr(*rhs, env)) {
+                (Object::Integer(num1), Object::Integer(num2)) => {
+                    if num2 < 0 || num2 > u32::MAX as i64 {
+                        panic!("Exponent out of range: {}", num2);
+                    }
+                    match num1.checked_pow(num2 as u32) {
+                        Some(num) => Object::Integer(num),
+                        None => panic!("Integer overflow in {} ** {}", num1, num2),
+                    }
+                }
+                _ => panic!("Can only raise integer literals to a power"),
+            }
+        }
+        Expression::Infix(Infix::BitAnd, lhs, rhs) => {
             match (eval_expr(*lhs, env), eval_expr(*rhs, env)) {
-                (Object::Integer(num1), Object::Integer(num2)) => Object::Integer(num1 / num2),
-                _ => panic!("Can only divide integer literals"),
+                (Object::Integer(num1), Object::Integer(num2)) => Object::Integer(num1 & num2),
+                _ => panic!("Can only use the '&' operator for integer literals"),
             }
         }
-        Expression::Infix(Infix::Multiply, lhs, rhs) => {
+        Expression::Infix(Infix::BitOr, lhs, rhs) => {
+            match (eval_expr(*lhs, env), eval_expr(*rhs, env)) {
+                (Object::Integer(num1), Object::Integer(num2)) => Object::Integer(num1 | num2),
+                _ => panic!("Can only use the '|' operator for integer literals"),
+            }
+        }
+        Expression::Infix(Infix::BitXor, lhs, rhs) => {
+            match (eval_expr(*lhs, env), eval_expr(*rhs, env)) {
+                (Object::Integer(num1), Object::Integer(num2)) => Object::Integer(num1 ^ num2),
+                _ => panic!("Can only use the '^' operator for integer literals"),
+            }
+        }
+        Expression::Infix(Infix::ShiftLeft, lhs, rhs) => {
+            match (eval_expr(*lhs, env), eval_expr(*rhs, env)) {
+                (Object::Integer(num1), Object::Integer(num2)) => {
+                    Object::Integer(num1 << shift_amount(num2))
+                }
+                _ => panic!("Can only shift integer literals"),
+            }
+        }
+        Expression::Infix(Infix::ShiftRight, lhs, rhs) => {
             match (eval_expr(*lhs, env), eval_expr(*rhs, env)) {
-                (Object::Integer(num1), Object::Integer(num2)) => Object::Integer(num1 * num2),
-                _ => panic!("Can only multiply integer literals"),
+                (Object::Integer(num1), Object::Integer(num2)) => {
+                    Object::Integer(num1 >> shift_amount(num2))
+                }
+                _ => panic!("Can only shift integer literals"),
             }
         }
         Expression::Infix(Infix::Equal, lhs, rhs) => {
             match (eval_expr(*lhs, env), eval_expr(*rhs, env)) {
                 (Object::Integer(num1), Object::Integer(num2)) => Object::Boolean(num1 == num2),
-                _ => panic!("Can only compare integer literals"),
+                (Object::String(str1), Object::String(str2)) => Object::Boolean(str1 == str2),
+                (Object::Boolean(bool1), Object::Boolean(bool2)) => Object::Boolean(bool1 == bool2),
+                (lhs, rhs) => match float_operands(&lhs, &rhs) {
+                    Some((num1, num2)) => Object::Boolean(num1 == num2),
+                    None => panic!("Can only compare integer literals"),
+                },
             }
         }
         Expression::Infix(Infix::NotEqual, lhs, rhs) => {
             match (eval_expr(*lhs, env), eval_expr(*rhs, env)) {
                 (Object::Integer(num1), Object::Integer(num2)) => Object::Boolean(num1 != num2),
-                _ => panic!("Can only compare integer literals"),
+                (Object::String(str1), Object::String(str2)) => Object::Boolean(str1 != str2),
+                (Object::Boolean(bool1), Object::Boolean(bool2)) => Object::Boolean(bool1 != bool2),
+                (lhs, rhs) => match float_operands(&lhs, &rhs) {
+                    Some((num1, num2)) => Object::Boolean(num1 != num2),
+                    None => panic!("Can only compare integer literals"),
+                },
             }
         }
         Expression::Infix(Infix::MoreThanAndEqual, lhs, rhs) => {
             match (eval_expr(*lhs, env), eval_expr(*rhs, env)) {
                 (Object::Integer(num1), Object::Integer(num2)) => Object::Boolean(num1 >= num2),
-                _ => panic!("Can only compare integer literals"),
+                (lhs, rhs) => match float_operands(&lhs, &rhs) {
+                    Some((num1, num2)) => Object::Boolean(num1 >= num2),
+                    None => panic!("Can only compare integer literals"),
+                },
             }
         }
         Expression::Infix(Infix::MoreThan, lhs, rhs) => {
             match (eval_expr(*lhs, env), eval_expr(*rhs, env)) {
                 (Object::Integer(num1), Object::Integer(num2)) => Object::Boolean(num1 > num2),
-                _ => panic!("Can only compare integer literals"),
+                (lhs, rhs) => match float_operands(&lhs, &rhs) {
+                    Some((num1, num2)) => Object::Boolean(num1 > num2),
+                    None => panic!("Can only compare integer literals"),
+                },
             }
         }
         Expression::Infix(Infix::LessThanAndEqual, lhs, rhs) => {
             match (eval_expr(*lhs, env), eval_expr(*rhs, env)) {
                 (Object::Integer(num1), Object::Integer(num2)) => Object::Boolean(num1 <= num2),
-                _ => panic!("Can only compare integer literals"),
+                (lhs, rhs) => match float_operands(&lhs, &rhs) {
+                    Some((num1, num2)) => Object::Boolean(num1 <= num2),
+                    None => panic!("Can only compare integer literals"),
+                },
             }
         }
         Expression::Infix(Infix::LessThan, lhs, rhs) => {
             match (eval_expr(*lhs, env), eval_expr(*rhs, env)) {
                 (Object::Integer(num1), Object::Integer(num2)) => Object::Boolean(num1 < num2),
-                _ => panic!("Can only compare integer literals"),
+                (lhs, rhs) => match float_operands(&lhs, &rhs) {
+                    Some((num1, num2)) => Object::Boolean(num1 < num2),
+                    None => panic!("Can only compare integer literals"),
+                },
             }
         }
         Expression::Infix(Infix::Assign, lhs, rhs) => {
-            let expr_lhs = lhs.clone();
-            let expr_rhs = rhs.clone();
-            match (*expr_lhs, eval_expr(*expr_rhs, env)) {
-                (Expression::Index(arr, first_index, second_index), Object::Integer(number)) => {
-                    let arr_copy = arr.clone();
-                    let arr_name;
-                    match eval_expr(*arr_copy, env) {
-                        Object::String(_name) => arr_name = _name,
-                        _ => panic!("Unrecognized Identifier"),
-                    }
-
-                    let first_index_clone = first_index.clone();
-                    let second_index_clone = second_index.clone();
-
-                    match eval_expr(Expression::Index(arr, first_index, second_index), env) {
-                        Object::Integer(num) => {
-                            let array_obj = env.get(arr_name.as_str());
-                            let row;
-                            let col;
-                            match *second_index_clone {
-                                Some(expr) => {
-                                    let obj = eval_expr(expr, env);
-                                    match obj {
-                                        Object::Integer(num) => {
-                                            row = num;
-                                        }
-                                        _ => panic!("Invalid Index"),
-                                    }
-                                }
-                                _ => row = 0,
-                            }
-                            match eval_expr(*first_index_clone, env) {
-                                Object::Integer(num) => {
-                                    col = num;
-                                }
-                                _ => panic!("Invalid Index"),
-                            }
-
-                            match array_obj {
-                                Some(Object::Array(els)) => {
-                                    let mut final_arr = vec![];
-                                    for i in 0..els.len() {
-                                        let mut row_arr = vec![];
-                                        for j in 0..els[i].len() {
-                                            if i == row as usize && j == col as usize {
-                                                row_arr.push(Object::Integer(number));
-                                                continue;
-                                            }
-                                            match els[i][j] {
-                                                Object::Integer(num) => {
-                                                    row_arr.push(Object::Integer(num));
-                                                }
-                                                _ => (),
-                                            }
-                                        }
-                                        final_arr.push(row_arr);
-                                    }
-                                    env.set(arr_name, Object::Array(final_arr));
-                                    return Object::Integer(0);
-                                }
-                                _ => {
-                                    panic!("Unexpected Identifier");
-                                }
-                            }
-                        }
-                        _ => panic!("Invalid Index"),
-                    }
+            let value = eval_expr(*rhs, env);
+            assign(*lhs, value.clone(), env);
+            value
+        }
+        Expression::If(condition, consequence, alternative) => {
+            let condition = eval_expr(*condition, env);
+            if is_truthy(&condition, env.strict) {
+                eval_statements(consequence, env)
+            } else {
+                match alternative {
+                    Some(stmts) => eval_statements(stmts, env),
+                    None => Object::Null,
                 }
-                _ => (),
             }
-
-            let lhs_name;
-            match eval_expr(*lhs, env) {
-                Object::String(name) => lhs_name = name,
-                _ => panic!("Invalid assignment"),
+        }
+        Expression::Block(stmts) => {
+            env.enter_scope();
+            let value = eval_statements(stmts, env);
+            env.exit_scope();
+            value
+        }
+        Expression::StructLiteral(name, values) => {
+            let declared = match env.structs.get(&name) {
+                Some(fields) => fields.clone(),
+                None => panic!("Unknown struct {}", name),
+            };
+            for (index, (field, _)) in values.iter().enumerate() {
+                if !declared.contains(field) {
+                    panic!("Struct {} has no field {}", name, field);
+                }
+                if values[..index].iter().any(|(given, _)| given == field) {
+                    panic!("Duplicate field {} in {}", field, name);
+                }
             }
-            match eval_expr(*rhs, env) {
-                Object::Integer(num2) => {
-                    env.set(lhs_name, Object::Integer(num2));
-                    Object::Integer(num2)
+            let mut values: Vec<(String, Object)> = values
+                .into_iter()
+                .map(|(field, value)| (field, eval_expr(value, env)))
+                .collect();
+            let mut fields = vec![];
+            for field in declared {
+                match values.iter().position(|(given, _)| *given == field) {
+                    Some(index) => fields.push(values.remove(index)),
+                    None => panic!("Missing field {} in {} literal", field, name),
                 }
-                Object::Boolean(_bool) => {
-                    env.set(lhs_name, Object::Boolean(_bool));
-                    Object::Boolean(_bool)
+            }
+            Object::Struct(name, fields)
+        }
+        Expression::Field(object, field) => match eval_expr(*object, env) {
+            Object::Struct(name, fields) => {
+                match fields.into_iter().find(|(given, _)| *given == field) {
+                    Some((_, value)) => value,
+                    None => panic!("Struct {} has no field {}", name, field),
                 }
-                Object::Array(els) => {
-                    env.set(lhs_name, Object::Array(els));
-                    Object::Integer(0)
+            }
+            other => panic!("Cannot access field {} of {:?}", field, other),
+        },
+        Expression::Match(scrutinee, arms) => {
+            let value = eval_expr(*scrutinee, env);
+            for arm in arms {
+                let bindings = arm
+                    .patterns
+                    .iter()
+                    .filter_map(|pattern| match_pattern(pattern, &value))
+                    .next();
+                if let Some(bindings) = bindings {
+                    // The bindings only live as long as the arm body.
+                    env.enter_scope();
+                    for (name, val) in bindings {
+                        env.declare(name, val);
+                    }
+                    let result = eval_expr(arm.body, env);
+                    env.exit_scope();
+                    return result;
                 }
-                _ => panic!("Invalid assignment"),
             }
+            panic!("No match arm matched {:?}", value)
+        }
+        Expression::CompoundAssign(infix, target, value) => {
+            let target = resolve_target(*target, env);
+            eval_expr(Expression::compound_assign(infix, target, *value), env)
+        }
+        Expression::Postfix(postfix, target) => {
+            let target = resolve_target(*target, env);
+            let old = eval_expr(target.clone(), env);
+            let update =
+                Expression::compound_assign(postfix.infix(), target, Expression::Integer(1));
+            eval_expr(update, env);
+            old
         }
-        _ => Object::Null,
     }
 }
 
-fn eval_builtin(name: &str, args: Vec<Object>, env: &mut Env) -> Option<Object> {
-    match (name, args.as_slice()) {
-        ("print", [Object::Array(els)]) => {
-            print!("{}[2J", 27 as char); //clear terminal screen
-            let mut vec = vec![];
-            for row in els {
-                let mut row_arr = vec![];
-                for el in row {
-                    match el {
-                        Object::Integer(num) => row_arr.push(Object::Integer(*num)),
-                        _ => continue,
+/// Booleans are taken as they are, integers are true when non-zero and arrays,
+/// lists, tensors and maps when they hold at least one element. In strict mode only booleans are
+/// accepted.
+fn is_truthy(condition: &Object, strict: bool) -> bool {
+    match condition {
+        Object::Boolean(_bool) => *_bool,
+        _ if strict => panic!(
+            "Condition must be a boolean in strict mode, got {:?}",
+            condition
+        ),
+        Object::Integer(num) => *num != 0,
+        Object::Float(num) => *num != 0.0,
+        Object::Array(els) => els.iter().any(|row| !row.is_empty()),
+        Object::List(els) => !els.is_empty(),
+        Object::Tensor(_, data) => !data.is_empty(),
+        Object::Map(map) => !map.is_empty(),
+        Object::Null => false,
+        _ => panic!("{:?} cannot be used as a condition", condition),
+    }
+}
+
+/// The assertion builtins, which report where the failing call is.
+fn eval_assertion(name: &str, args: &[Object], span: Span, env: &Env) -> Object {
+    match (name, args) {
+        ("assert", [cond]) | ("assert", [cond, _]) => {
+            assertions::assert(args, is_truthy(cond, env.strict), span)
+        }
+        ("assert_eq", [lhs, rhs]) => assertions::assert_eq(lhs, rhs, span),
+        ("assert_matrix_close", [lhs, rhs, eps]) => {
+            assertions::assert_matrix_close(lhs, rhs, eps, span)
+        }
+        _ => panic!("Unrecognized function {:#?}", name),
+    }
+}
+
+/// Returns the variables bound by `pattern` if it matches `value`.
+fn match_pattern(pattern: &Pattern, value: &Object) -> Option<Vec<(String, Object)>> {
+    match (pattern, value) {
+        (Pattern::Wildcard, _) => Some(vec![]),
+        (Pattern::Binding(name), _) => Some(vec![(name.clone(), value.clone())]),
+        (Pattern::Integer(expected), Object::Integer(num)) if expected == num => Some(vec![]),
+        (Pattern::Bool(expected), Object::Boolean(_bool)) if expected == _bool => Some(vec![]),
+        (Pattern::Array(rows), Object::Array(els)) => {
+            if rows.len() != els.len() {
+                return None;
+            }
+            let mut bindings = vec![];
+            for (row, cols) in rows.iter().zip(els.iter()) {
+                if row.len() != cols.len() {
+                    return None;
+                }
+                for (pattern, el) in row.iter().zip(cols.iter()) {
+                    match match_pattern(pattern, el) {
+                        Some(found) => bindings.extend(found),
+                        None => return None,
                     }
                 }
-                vec.push(row_arr);
-                println!("{:?}", vec);
             }
-
-            return Some(Object::Array(vec));
-        }
-        ("print", [Object::Integer(number)]) => {
-            print!("{}[2J", 27 as char);
-            println!("{}", number);
-            return Some(Object::Integer(*number));
+            Some(bindings)
         }
-        ("print", [Object::String(name)]) => {
-            print!("{}[2J", 27 as char);
-            let obj = env.get(name.as_str());
-            match &obj {
-                Some(object) => println!("{:?}", *object),
-                None => panic!("Unrecognized Identifier"),
+        (Pattern::List(patterns), Object::List(els)) => {
+            if patterns.len() != els.len() {
+                return None;
             }
-            return obj;
+            let mut bindings = vec![];
+            for (pattern, el) in patterns.iter().zip(els.iter()) {
+                match match_pattern(pattern, el) {
+                    Some(found) => bindings.extend(found),
+                    None => return None,
+                }
+            }
+            Some(bindings)
         }
-        _ => (),
+        _ => None,
     }
-    let mut params: Vec<Object> = vec![];
-    for arg in &args {
-        if let Object::String(val) = arg {
-            params.push(env.get(val).unwrap());
+}
+
+/// Evaluates the index operands of an assignment target and puts their values
+/// in their place, so that reading and then writing the target, as `+=` and
+/// `++` do, evaluates `m[f(), 0]` or `l[g()]` only once.
+fn resolve_target(target: Expression, env: &mut Env) -> Expression {
+    match target {
+        Expression::Index(container, indices) => {
+            let container = resolve_target(*container, env);
+            let mut literal = |index: Option<Box<Expression>>| {
+                index.map(|index| Box::new(index_literal(eval_expr(*index, env))))
+            };
+            let indices = indices
+                .into_iter()
+                .map(|index| match index {
+                    Expression::Slice(start, end, step) => {
+                        Expression::Slice(literal(start), literal(end), literal(step))
+                    }
+                    index => *literal(Some(Box::new(index))).unwrap(),
+                })
+                .collect();
+            Expression::Index(Box::new(container), indices)
         }
-    }
-    match (name, params.as_slice()) {
-        ("size", [Object::Array(els)]) => {
-            let mut total = 0;
-            for row in els {
-                total += row.len();
-            }
-            Some(Object::Integer(total as i64))
+        Expression::Field(object, field) => {
+            Expression::Field(Box::new(resolve_target(*object, env)), field)
         }
-        ("max", [Object::Array(els)]) => {
-            let mut max = i64::min_value();
-            for row in els {
-                for el in row {
-                    match el {
-                        Object::Integer(num) => {
-                            if *num > max {
-                                max = *num;
+        target => target,
+    }
+}
+
+fn index_literal(index: Object) -> Expression {
+    match index {
+        Object::Integer(num) => Expression::Integer(num),
+        Object::String(string) => Expression::String(string),
+        Object::Boolean(_bool) => Expression::Bool(_bool),
+        other => panic!("{:?} cannot be used as an index", other),
+    }
+}
+
+fn assign(target: Expression, value: Object, env: &mut Env) {
+    match target {
+        Expression::Identifier(name) => env.set(name, value),
+        Expression::Index(input_array, indices) => {
+            let container = eval_expr(*input_array.clone(), env);
+            let args = eval_indices(indices, env);
+            let indices = match plain_indices(&args) {
+                Some(indices) => indices,
+                None => {
+                    let updated = assign_slice(container, &args, value);
+                    return assign(*input_array, updated, env);
+                }
+            };
+            let updated = match (container, indices.as_slice()) {
+                (container @ Object::Array(_), [_]) => assign_slice(container, &args, value),
+                (Object::Array(mut els), [first, second]) => {
+                    let (row, col) = cell_position(
+                        &els,
+                        integer_index(first.clone()),
+                        integer_index(second.clone()),
+                    );
+                    els[row][col] = value;
+                    Object::Array(els)
+                }
+                (Object::List(mut els), [first]) => {
+                    let index = list_position(&els, integer_index(first.clone()));
+                    els[index] = value;
+                    Object::List(els)
+                }
+                (Object::Map(mut map), [first]) => {
+                    map.insert(MapKey::from_object(first.clone()), value);
+                    Object::Map(map)
+                }
+                (Object::Tensor(shape, mut data), indices) => {
+                    let indices: Vec<i64> = indices.iter().cloned().map(integer_index).collect();
+                    let (range, rest) = tensor::locate(&shape, &indices);
+                    if rest.is_empty() {
+                        data[range.start] = value;
+                    } else {
+                        match value {
+                            Object::Tensor(ref given, ref values) if *given == rest => {
+                                data.splice(range, values.iter().cloned());
                             }
+                            _ => panic!("Expected a tensor of shape {:?}, got {:?}", rest, value),
                         }
-                        _ => continue,
                     }
+                    Object::Tensor(shape, data)
                 }
-            }
-            Some(Object::Integer(max))
+                (container, indices) => panic!(
+                    "Cannot index {:?} with {} indices",
+                    container,
+                    indices.len()
+                ),
+            };
+            assign(*input_array, updated, env);
         }
-        ("min", [Object::Array(els)]) => {
-            let mut min = i64::max_value();
-            for row in els {
-                for el in row {
-                    match el {
-                        Object::Integer(num) => {
-                            if (*num < min) {
-                                min = *num;
-                            }
-                        }
-                        _ => continue,
+        Expression::Field(object, field) => {
+            let updated = match eval_expr(*object.clone(), env) {
+                Object::Struct(name, mut fields) => {
+                    match fields.iter_mut().find(|(given, _)| *given == field) {
+                        Some((_, slot)) => *slot = value,
+                        None => panic!("Struct {} has no field {}", name, field),
                     }
+                    Object::Struct(name, fields)
                 }
+                other => panic!("Cannot assign field {} of {:?}", field, other),
+            };
+            assign(*object, updated, env);
+        }
+        _ => panic!("Invalid assignment"),
+    }
+}
+
+/// `+ - * /` on two values. When either side is a matrix or a tensor the
+/// operator is applied element-wise, broadcasting scalars, rows and columns;
+/// the result is a tensor if either operand is one and a matrix otherwise.
+fn eval_arithmetic(infix: Infix, lhs: Object, rhs: Object) -> Object {
+    if let Some((num1, num2)) = float_operands(&lhs, &rhs) {
+        return Object::Float(match infix {
+            Infix::Plus => num1 + num2,
+            Infix::Minus => num1 - num2,
+            Infix::Multiply => num1 * num2,
+            Infix::Divide => num1 / num2,
+            infix => panic!("{} is not an arithmetic operator", infix),
+        });
+    }
+    match (infix, lhs, rhs) {
+        (infix, lhs, rhs) if is_elementwise_operand(&lhs) || is_elementwise_operand(&rhs) => {
+            let is_tensor = matches!(lhs, Object::Tensor(..)) || matches!(rhs, Object::Tensor(..));
+            let (lhs_shape, lhs_data) = elementwise_operand(lhs);
+            let (rhs_shape, rhs_data) = elementwise_operand(rhs);
+            let (shape, data) =
+                tensor::broadcast((&lhs_shape, &lhs_data), (&rhs_shape, &rhs_data), |l, r| {
+                    eval_arithmetic(infix.clone(), l.clone(), r.clone())
+                });
+            if is_tensor {
+                Object::Tensor(shape, data)
+            } else {
+                Object::Array(tensor::to_matrix(&shape, &data))
             }
-            Some(Object::Integer(min))
         }
-        ("sum", [Object::Array(els)]) => {
-            let mut sum: i64 = 0;
-            for row in els {
-                for el in row {
-                    match el {
-                        Object::Integer(num) => sum += num,
-                        _ => continue,
-                    }
-                }
+        (Infix::Plus, Object::Integer(num1), Object::Integer(num2)) => Object::Integer(num1 + num2),
+        (Infix::Plus, ..) => panic!("Can only add integer literals"),
+        (Infix::Minus, Object::Integer(num1), Object::Integer(num2)) => {
+            Object::Integer(num1 - num2)
+        }
+        (Infix::Minus, ..) => panic!("Can only subtract integer literals"),
+        (Infix::Divide, Object::Integer(_), Object::Integer(0)) => panic!("Division by zero"),
+        (Infix::Divide, Object::Integer(num1), Object::Integer(num2)) => {
+            match num1.checked_div(num2) {
+                Some(num) => Object::Integer(num),
+                None => panic!("Integer overflow in {} / {}", num1, num2),
             }
-            Some(Object::Integer(sum))
         }
-        ("constant_product", [Object::Array(els), Object::Integer(_const)]) => {
-            let mut vec = vec![];
-            for row in els {
-                let mut row_arr = vec![];
-                for el in row {
-                    match el {
-                        Object::Integer(num) => row_arr.push(Object::Integer(*num * _const)),
-                        _ => continue,
+        (Infix::Divide, ..) => panic!("Can only divide integer literals"),
+        (Infix::Multiply, Object::Integer(num1), Object::Integer(num2)) => {
+            Object::Integer(num1 * num2)
+        }
+        (Infix::Multiply, ..) => panic!("Can only multiply integer literals"),
+        (infix, ..) => panic!("{} is not an arithmetic operator", infix),
+    }
+}
+
+/// The matrix product of an `n x m` and an `m x p` matrix.
+fn matmul(lhs: &[Vec<Object>], rhs: &[Vec<Object>]) -> Vec<Vec<Object>> {
+    let (lhs_shape, _) = tensor::from_matrix(lhs);
+    let (rhs_shape, _) = tensor::from_matrix(rhs);
+    if lhs_shape[1] != rhs_shape[0] {
+        panic!(
+            "Cannot multiply a {}x{} matrix by a {}x{} matrix",
+            lhs_shape[0], lhs_shape[1], rhs_shape[0], rhs_shape[1]
+        );
+    }
+    lhs.iter()
+        .map(|row| {
+            (0..rhs_shape[1])
+                .map(|col| {
+                    let mut products = row
+                        .iter()
+                        .zip(rhs)
+                        .map(|(l, r)| eval_arithmetic(Infix::Multiply, l.clone(), r[col].clone()));
+                    match products.next() {
+                        Some(first) => products.fold(first, |sum, product| {
+                            eval_arithmetic(Infix::Plus, sum, product)
+                        }),
+                        None => Object::Integer(0),
                     }
-                }
-                vec.push(row_arr);
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// A matrix or rank 2 tensor operand of `@`, in the matrix form.
+fn matmul_operand(object: Object) -> Vec<Vec<Object>> {
+    match object {
+        Object::Array(els) => els,
+        Object::Tensor(shape, data) => tensor::to_matrix(&shape, &data),
+        _ => panic!("Can only use the '@' operator for matrices"),
+    }
+}
+
+/// Both operands as floats when at least one of them is a float and the other
+/// a number; integers are only promoted when mixed with floats.
+fn float_operands(lhs: &Object, rhs: &Object) -> Option<(f64, f64)> {
+    match (lhs, rhs) {
+        (Object::Float(num1), Object::Float(num2)) => Some((*num1, *num2)),
+        (Object::Float(num1), Object::Integer(num2)) => Some((*num1, *num2 as f64)),
+        (Object::Integer(num1), Object::Float(num2)) => Some((*num1 as f64, *num2)),
+        _ => None,
+    }
+}
+
+fn is_elementwise_operand(object: &Object) -> bool {
+    matches!(object, Object::Array(_) | Object::Tensor(..))
+}
+
+/// The shape and elements of an operand of an element-wise operation; any
+/// value other than a matrix or tensor counts as a scalar.
+fn elementwise_operand(object: Object) -> (Vec<usize>, Vec<Object>) {
+    match object {
+        Object::Array(els) => tensor::from_matrix(&els),
+        Object::Tensor(shape, data) => (shape, data),
+        scalar => (vec![], vec![scalar]),
+    }
+}
+
+/// An evaluated index of `a[...]`: a position or key, or a slice.
+enum IndexArg {
+    At(Object),
+    Slice(Option<i64>, Option<i64>, Option<i64>),
+}
+
+fn eval_indices(indices: Vec<Expression>, env: &mut Env) -> Vec<IndexArg> {
+    let mut args = vec![];
+    for index in indices {
+        args.push(match index {
+            Expression::Slice(start, end, step) => {
+                let mut bound = |expr: Option<Box<Expression>>| {
+                    expr.map(|expr| integer_index(eval_expr(*expr, env)))
+                };
+                IndexArg::Slice(bound(start), bound(end), bound(step))
             }
+            expr => IndexArg::At(eval_expr(expr, env)),
+        });
+    }
+    args
+}
 
-            Some(Object::Array(vec))
-        }
-        ("dot_product", [Object::Array(first_arr), Object::Array(second_arr)]) => {
-            if !check_array_size(first_arr) || !check_array_size(second_arr) {
-                panic!("Array's rows need to be the same size");
+/// The indices as plain values, or `None` if any of them is a slice.
+fn plain_indices(indices: &[IndexArg]) -> Option<Vec<Object>> {
+    indices
+        .iter()
+        .map(|index| match index {
+            IndexArg::At(at) => Some(at.clone()),
+            IndexArg::Slice(..) => None,
+        })
+        .collect()
+}
+
+/// The positions of a dimension of length `len` picked by `index`. Slices are
+/// zero-based and exclusive of their end, which is clamped to `len`.
+fn axis_positions(index: &IndexArg, len: usize) -> Vec<usize> {
+    match index {
+        IndexArg::At(at) => {
+            let at = integer_index(at.clone());
+            if at < 0 || at as usize >= len {
+                panic!("Index {} out of range for length {}", at, len);
             }
-            if first_arr[0].len() != second_arr.len() {
-                panic!("The number of columns of the 1st matrix must equal the number of rows of the 2nd matrix.");
+            vec![at as usize]
+        }
+        IndexArg::Slice(start, end, step) => {
+            let step = step.unwrap_or(1);
+            if step <= 0 {
+                panic!("Slice step must be positive, got {}", step);
             }
+            let bound = |bound: i64| {
+                if bound < 0 {
+                    panic!("Slice bounds must be non-negative, got {}", bound);
+                }
+                (bound as usize).min(len)
+            };
+            let start = start.map_or(0, bound);
+            let end = end.map_or(len, bound);
+            (start..end).step_by(step as usize).collect()
+        }
+    }
+}
 
-            let mut vec: Vec<Vec<Object>> = vec![];
+/// The cells of a matrix picked by `m[rows]` or `m[rows, cols]`, by row.
+fn matrix_cells(els: &[Vec<Object>], indices: &[IndexArg]) -> Vec<Vec<(usize, usize)>> {
+    let (rows, cols) = match indices {
+        [rows] => (rows, None),
+        [rows, cols] => (rows, Some(cols)),
+        _ => panic!("Cannot index an array with {} indices", indices.len()),
+    };
+    axis_positions(rows, els.len())
+        .into_iter()
+        .map(|row| {
+            let picked = match cols {
+                Some(cols) => axis_positions(cols, els[row].len()),
+                None => (0..els[row].len()).collect(),
+            };
+            picked.into_iter().map(|col| (row, col)).collect()
+        })
+        .collect()
+}
 
-            for i in 0..first_arr.len() {
-                let mut arr = vec![];
-                for j in 0..second_arr[0].len() {
-                    arr.push(Object::Integer(0));
+fn tensor_axes(shape: &[usize], indices: &[IndexArg]) -> Vec<(Vec<usize>, bool)> {
+    if indices.len() > shape.len() {
+        panic!(
+            "Cannot index a rank {} tensor with {} indices",
+            shape.len(),
+            indices.len()
+        );
+    }
+    shape
+        .iter()
+        .enumerate()
+        .map(|(dim, &len)| match indices.get(dim) {
+            Some(index) => (
+                axis_positions(index, len),
+                matches!(index, IndexArg::Slice(..)),
+            ),
+            None => ((0..len).collect(), true),
+        })
+        .collect()
+}
+
+/// Reads `a[...]` where at least one index is a slice, and `m[i]` on a
+/// matrix. Lists give lists, matrices give matrices and tensors give tensors without the dimensions
+/// picked by a single index.
+fn select_slice(container: Object, indices: &[IndexArg]) -> Object {
+    match (container, indices) {
+        (Object::List(els), [index]) => Object::List(
+            axis_positions(index, els.len())
+                .into_iter()
+                .map(|pos| els[pos].clone())
+                .collect(),
+        ),
+        (Object::Array(els), indices) => Object::Array(
+            matrix_cells(&els, indices)
+                .into_iter()
+                .map(|cells| {
+                    cells
+                        .into_iter()
+                        .map(|(row, col)| els[row][col].clone())
+                        .collect()
+                })
+                .collect(),
+        ),
+        (Object::Tensor(shape, data), indices) => {
+            let (offsets, rest) = tensor::select(&shape, &tensor_axes(&shape, indices));
+            Object::Tensor(rest, offsets.into_iter().map(|o| data[o].clone()).collect())
+        }
+        (container, indices) => panic!(
+            "Cannot slice {:?} with {} indices",
+            container,
+            indices.len()
+        ),
+    }
+}
+
+/// Assigns through a slice. The value must have the shape of the selection,
+/// except that a single value is written to every cell of a matrix or tensor
+/// slice.
+fn assign_slice(container: Object, indices: &[IndexArg], value: Object) -> Object {
+    match (container, indices) {
+        (Object::List(mut els), [index]) => {
+            let positions = axis_positions(index, els.len());
+            match value {
+                Object::List(values) if values.len() == positions.len() => {
+                    for (pos, value) in positions.into_iter().zip(values) {
+                        els[pos] = value;
+                    }
                 }
-                vec.push(arr);
+                _ => panic!(
+                    "Expected a list of length {}, got {:?}",
+                    positions.len(),
+                    value
+                ),
             }
-
-            for row in 0..first_arr.len() {
-                for col in 0..second_arr[0].len() {
-                    for k in 0..first_arr[0].len() {
-                        match (&first_arr[row][k], &second_arr[k][col]) {
-                            (Object::Integer(num1), Object::Integer(num2)) => {
-                                vec[row][col] = Object::Integer(num1 * num2)
-                            }
-                            _ => (),
+            Object::List(els)
+        }
+        (Object::Array(mut els), indices) => {
+            let cells = matrix_cells(&els, indices);
+            match value {
+                Object::Array(values)
+                    if values.len() == cells.len()
+                        && values.iter().zip(&cells).all(|(v, c)| v.len() == c.len()) =>
+                {
+                    for (row, values) in cells.into_iter().zip(values) {
+                        for ((row, col), value) in row.into_iter().zip(values) {
+                            els[row][col] = value;
                         }
                     }
                 }
+                Object::Array(_) | Object::List(_) | Object::Tensor(..) => panic!(
+                    "Expected an array of shape {}x{}, got {:?}",
+                    cells.len(),
+                    cells.first().map_or(0, |row| row.len()),
+                    value
+                ),
+                value => {
+                    for (row, col) in cells.into_iter().flatten() {
+                        els[row][col] = value.clone();
+                    }
+                }
             }
-
-            Some(Object::Array(vec))
+            Object::Array(els)
         }
-        ("constant_division", [Object::Array(els), Object::Integer(_const)]) => {
-            let mut vec = vec![];
-            for row in els {
-                let mut row_arr = vec![];
-                for el in row {
-                    match el {
-                        Object::Integer(num) => row_arr.push(Object::Integer(*num / _const)),
-                        _ => continue,
+        (Object::Tensor(shape, mut data), indices) => {
+            let (offsets, rest) = tensor::select(&shape, &tensor_axes(&shape, indices));
+            match value {
+                Object::Tensor(given, values) if given == rest => {
+                    for (offset, value) in offsets.into_iter().zip(values) {
+                        data[offset] = value;
+                    }
+                }
+                Object::Tensor(given, _) => panic!(
+                    "Expected a tensor of shape {:?}, got shape {:?}",
+                    rest, given
+                ),
+                value => {
+                    for offset in offsets {
+                        data[offset] = value.clone();
                     }
                 }
-                vec.push(row_arr);
             }
-            Some(Object::Array(vec))
+            Object::Tensor(shape, data)
         }
-        ("transpose_matrix", [Object::Array(els)]) => {
-            check_array_size(&els);
+        (container, indices) => panic!(
+            "Cannot slice {:?} with {} indices",
+            container,
+            indices.len()
+        ),
+    }
+}
+
+fn list_position(els: &[Object], index: i64) -> usize {
+    if index < 0 || index as usize >= els.len() {
+        panic!("List index {} out of range for length {}", index, els.len());
+    }
+    index as usize
+}
+
+fn integer_index(index: Object) -> i64 {
+    match index {
+        Object::Integer(num) => num,
+        _ => panic!("Invalid index"),
+    }
+}
+
+/// Resolves `m[i, j]` to row `i`, column `j`.
+fn cell_position(els: &[Vec<Object>], row: i64, col: i64) -> (usize, usize) {
+    if row < 0 || row as usize >= els.len() {
+        panic!("Array rows out of range");
+    }
+    if col < 0 || col as usize >= els[row as usize].len() {
+        panic!("Array column out of range");
+    }
+    (row as usize, col as usize)
+}
+
+/// Calls a user function with evaluated arguments. The body runs in a fresh
+/// scope holding the parameters and the function itself, so it can recurse,
+/// and sees the functions declared at the top level. Functions do not capture
+/// the scope they were defined in: any other value they need must be passed
+/// as an argument.
+pub fn call_function(function: Object, args: Vec<Object>, env: &mut Env) -> Object {
+    match function {
+        Object::Function(name, params, body) => {
+            if params.len() != args.len() {
+                panic!("Wrong number of params");
+            }
+            let mut scope = env.new_scope();
+            scope.set(
+                name.clone(),
+                Object::Function(name, params.clone(), body.clone()),
+            );
+            for (param, arg) in params.into_iter().zip(args) {
+                scope.set(param, arg);
+            }
+            eval_return(body, &mut scope)
+        }
+        other => panic!("{} is not callable", other.repr()),
+    }
+}
+
+/// The kind of collection `collection_items` took apart.
+#[derive(Clone, Copy)]
+enum Collection {
+    List,
+    Matrix,
+    Map,
+}
+
+/// The items the collection builtins iterate over: the elements of a list,
+/// the rows of a matrix as lists, or the entries of a map as `[key, value]`
+/// lists in key order.
+fn collection_items(name: &str, xs: &Object) -> (Vec<Object>, Collection) {
+    match xs {
+        Object::List(els) => (els.clone(), Collection::List),
+        Object::Array(els) => (
+            els.iter().cloned().map(Object::List).collect(),
+            Collection::Matrix,
+        ),
+        Object::Map(map) => (
+            map.iter()
+                .map(|(key, value)| Object::List(vec![key.to_object(), value.clone()]))
+                .collect(),
+            Collection::Map,
+        ),
+        other => panic!(
+            "{} expects a list, a matrix or a map, got {:?}",
+            name, other
+        ),
+    }
+}
+
+/// Undoes `collection_items` for builtins that keep the items as they are,
+/// such as `filter` and `sort`.
+fn from_collection_items(items: Vec<Object>, kind: Collection) -> Object {
+    let unpack = |item| match item {
+        Object::List(els) => els,
+        _ => unreachable!(),
+    };
+    match kind {
+        Collection::List => Object::List(items),
+        Collection::Matrix => Object::Array(items.into_iter().map(unpack).collect()),
+        Collection::Map => Object::Map(
+            items
+                .into_iter()
+                .map(|item| {
+                    let mut pair = unpack(item).into_iter();
+                    let key = MapKey::from_object(pair.next().unwrap());
+                    (key, pair.next().unwrap())
+                })
+                .collect(),
+        ),
+    }
+}
+
+/// `sort` and `reverse` give a map's entries a new order, which only a
+/// list of `[key, value]` pairs can keep.
+fn ordered(kind: Collection) -> Collection {
+    match kind {
+        Collection::Map => Collection::List,
+        kind => kind,
+    }
+}
+
+/// The ordering used by `sort`: numbers, strings and booleans among
+/// themselves, and lists element by element.
+fn compare_objects(lhs: &Object, rhs: &Object) -> Ordering {
+    match (lhs, rhs) {
+        (Object::String(str1), Object::String(str2)) => str1.cmp(str2),
+        (Object::Boolean(bool1), Object::Boolean(bool2)) => bool1.cmp(bool2),
+        (Object::List(els1), Object::List(els2)) => {
+            for (el1, el2) in els1.iter().zip(els2) {
+                match compare_objects(el1, el2) {
+                    Ordering::Equal => continue,
+                    ordering => return ordering,
+                }
+            }
+            els1.len().cmp(&els2.len())
+        }
+        (Object::Integer(num1), Object::Integer(num2)) => num1.cmp(num2),
+        _ => match float_operands(lhs, rhs).and_then(|(num1, num2)| num1.partial_cmp(&num2)) {
+            Some(ordering) => ordering,
+            None => panic!("Cannot compare {:?} with {:?}", lhs, rhs),
+        },
+    }
+}
+
+fn eval_builtin(name: &str, args: Vec<Object>, env: &mut Env) -> Option<Object> {
+    match (name, args.as_slice()) {
+        ("print", args) | ("println", args) => {
+            let text: Vec<String> = args.iter().map(format::to_text).collect();
+            let mut stdout = io::stdout();
+            write!(stdout, "{}", text.join(" ")).unwrap();
+            if name == "println" {
+                writeln!(stdout).unwrap();
+            }
+            stdout.flush().unwrap();
+            return Some(match args {
+                [object] => object.clone(),
+                _ => Object::Null,
+            });
+        }
+        ("format", [Object::String(template), args @ ..]) => {
+            return Some(Object::String(format::format(template, args)));
+        }
+        ("clear", []) => {
+            print!("{}[2J{}[H", 27 as char, 27 as char);
+            io::stdout().flush().unwrap();
+            return Some(Object::Null);
+        }
+        _ => (),
+    }
+    match (name, args.as_slice()) {
+        ("size", [Object::Map(map)]) => Some(Object::Integer(map.len() as i64)),
+        ("keys", [Object::Map(map)]) => Some(Object::List(
+            map.keys().map(|key| key.to_object()).collect(),
+        )),
+        ("values", [Object::Map(map)]) => Some(Object::List(map.values().cloned().collect())),
+        ("has_key", [Object::Map(map), key]) => {
+            let key = MapKey::from_object(key.clone());
+            Some(Object::Boolean(map.contains_key(&key)))
+        }
+        // Values are passed by copy, so `remove` returns the map without
+        // `key`: `m = remove(m, "k");`.
+        ("remove", [Object::Map(map), key]) => {
+            let mut map = map.clone();
+            map.remove(&MapKey::from_object(key.clone()));
+            Some(Object::Map(map))
+        }
+        ("size", [Object::List(els)]) | ("len", [Object::List(els)]) => {
+            Some(Object::Integer(els.len() as i64))
+        }
+        ("len", [Object::String(string)]) => Some(Object::Integer(string.chars().count() as i64)),
+        ("len", [Object::Map(map)]) => Some(Object::Integer(map.len() as i64)),
+        // Like maps, lists are values: `push` and `pop` return the updated
+        // list instead of changing their argument, so taking the last
+        // element off is `let top = last(xs); xs = pop(xs);`.
+        ("push", [Object::List(els), value]) => {
+            let mut els = els.clone();
+            els.push(value.clone());
+            Some(Object::List(els))
+        }
+        ("pop", [Object::List(els)]) => {
+            if els.is_empty() {
+                panic!("Cannot pop from an empty list");
+            }
+            Some(Object::List(els[..els.len() - 1].to_vec()))
+        }
+        ("last", [Object::List(els)]) => match els.last() {
+            Some(last) => Some(last.clone()),
+            None => panic!("Cannot take the last element of an empty list"),
+        },
+        ("concat", [Object::List(first), Object::List(second)]) => {
+            let mut els = first.clone();
+            els.extend(second.iter().cloned());
+            Some(Object::List(els))
+        }
+        ("slice", [Object::List(els), Object::Integer(start), Object::Integer(end)]) => {
+            if *start < 0 || *start > *end || *end as usize > els.len() {
+                panic!(
+                    "Invalid slice {}..{} of a list of length {}",
+                    start,
+                    end,
+                    els.len()
+                );
+            }
+            Some(Object::List(els[*start as usize..*end as usize].to_vec()))
+        }
+        ("zeros", [Object::Integer(rows), Object::Integer(cols)]) => {
+            Some(filled_matrix(*rows, *cols, Object::Integer(0)))
+        }
+        ("ones", [Object::Integer(rows), Object::Integer(cols)]) => {
+            Some(filled_matrix(*rows, *cols, Object::Integer(1)))
+        }
+        ("identity", [Object::Integer(n)]) => {
+            let n = matrix_size(*n);
+            Some(Object::Array(
+                (0..n)
+                    .map(|row| {
+                        (0..n)
+                            .map(|col| Object::Integer((row == col) as i64))
+                            .collect()
+                    })
+                    .collect(),
+            ))
+        }
+        ("diag", [Object::List(els)]) | ("diag", [Object::Tensor(_, els)]) => {
+            let mut vec = vec![vec![Object::Integer(0); els.len()]; els.len()];
+            for (i, el) in els.iter().enumerate() {
+                vec[i][i] = el.clone();
+            }
+            Some(Object::Array(vec))
+        }
+        ("diag", [Object::Array(els)]) => Some(Object::List(
+            els.iter()
+                .enumerate()
+                .filter_map(|(i, row)| row.get(i).cloned())
+                .collect(),
+        )),
+        ("arange", [start, stop]) => Some(arange(start, stop, &Object::Integer(1))),
+        ("arange", [start, stop, step]) => Some(arange(start, stop, step)),
+        ("linspace", [start, stop, Object::Integer(count)]) => {
+            let (start, stop) = (number_value(start), number_value(stop));
+            let count = matrix_size(*count);
+            let step = if count > 1 {
+                (stop - start) / (count - 1) as f64
+            } else {
+                0.0
+            };
+            Some(Object::List(
+                (0..count)
+                    .map(|i| Object::Float(start + i as f64 * step))
+                    .collect(),
+            ))
+        }
+        (
+            "random_matrix",
+            [Object::Integer(rows), Object::Integer(cols), lo, hi, Object::Integer(seed)],
+        ) => {
+            let (rows, cols) = (matrix_size(*rows), matrix_size(*cols));
+            let mut rng = Rng::new(*seed as u64);
+            let mut next = || match (lo, hi) {
+                (Object::Integer(lo), Object::Integer(hi)) => {
+                    if lo > hi {
+                        panic!("random_matrix needs lo <= hi, got {} and {}", lo, hi);
+                    }
+                    Object::Integer(rng.range(*lo, *hi))
+                }
+                (lo, hi) => {
+                    let (lo, hi) = (number_value(lo), number_value(hi));
+                    if lo > hi {
+                        panic!("random_matrix needs lo <= hi, got {} and {}", lo, hi);
+                    }
+                    Object::Float(lo + (hi - lo) * rng.next_f64())
+                }
+            };
+            Some(Object::Array(
+                (0..rows)
+                    .map(|_| (0..cols).map(|_| next()).collect())
+                    .collect(),
+            ))
+        }
+        ("zeros", [shape]) => Some(filled_shape(shape, Object::Integer(0))),
+        ("ones", [shape]) => Some(filled_shape(shape, Object::Integer(1))),
+        ("fill", [shape, value]) => Some(filled_shape(shape, value.clone())),
+        ("shape", [Object::Tensor(shape, _)]) => Some(tensor::shape_to_object(shape)),
+        ("shape", [Object::List(els)]) => Some(tensor::shape_to_object(&[els.len()])),
+        ("shape", [Object::Array(els)]) => {
+            Some(tensor::shape_to_object(&tensor::from_matrix(els).0))
+        }
+        ("reshape", [Object::Tensor(_, data), shape]) => Some(tensor::reshape(
+            data.clone(),
+            tensor::shape_from_object(shape),
+        )),
+        ("reshape", [Object::List(els), shape]) => Some(tensor::reshape(
+            els.clone(),
+            tensor::shape_from_object(shape),
+        )),
+        ("reshape", [Object::Array(els), shape]) => {
+            let (_, data) = tensor::from_matrix(els);
+            Some(tensor::reshape(data, tensor::shape_from_object(shape)))
+        }
+        ("size", [Object::Tensor(_, data)]) => Some(Object::Integer(data.len() as i64)),
+        ("sum", [Object::Tensor(_, data)])
+        | ("max", [Object::Tensor(_, data)])
+        | ("min", [Object::Tensor(_, data)]) => {
+            eval_builtin(name, vec![Object::Array(vec![data.clone()])], env)
+        }
+        ("size", [Object::Array(els)]) => {
+            let mut total = 0;
+            for row in els {
+                total += row.len();
+            }
+            Some(Object::Integer(total as i64))
+        }
+        ("sum", [Object::Array(els)])
+        | ("max", [Object::Array(els)])
+        | ("min", [Object::Array(els)]) => Some(stats::reduce(name, els, None)),
+        ("constant_product", [Object::Array(els), Object::Integer(_const)]) => {
+            let m = integer_matrix(name, els);
+            Some(integer_array(
+                m.into_iter()
+                    .map(|row| row.into_iter().map(|num| num * _const).collect())
+                    .collect::<Vec<Vec<i64>>>(),
+            ))
+        }
+        ("dot_product", [Object::Array(first_arr), Object::Array(second_arr)]) => {
+            Some(Object::Array(matmul(first_arr, second_arr)))
+        }
+        ("constant_division", [Object::Array(els), Object::Integer(_const)]) => {
+            if *_const == 0 {
+                panic!("Division by zero");
+            }
+            let m = integer_matrix(name, els);
+            Some(integer_array(
+                m.into_iter()
+                    .map(|row| row.into_iter().map(|num| num / _const).collect())
+                    .collect::<Vec<Vec<i64>>>(),
+            ))
+        }
+        ("transpose_matrix", [Object::Array(els)]) => {
+            check_array_size(&els);
             let mut vec = vec![];
             for col in (0..els[0].len()) {
                 let mut arr = vec![];
                 for row in 0..els.len() {
-                    match els[row][col] {
-                        Object::Integer(num) => arr.push(Object::Integer(num)),
-                        _ => continue,
-                    }
+                    arr.push(els[row][col].clone());
                 }
                 vec.push(arr);
             }
@@ -450,22 +1403,17 @@ fn eval_builtin(name: &str, args: Vec<Object>, env: &mut Env) -> Option<Object>
                 panic!("Arrays should be the same size");
             }
 
-            let mut result = vec![];
-
-            for row in 0..first_arr.len() {
-                let mut arr = vec![];
-                for col in 0..first_arr[0].len() {
-                    match (&first_arr[row][col], &second_arr[row][col]) {
-                        (Object::Integer(num1), Object::Integer(num2)) => {
-                            arr.push(Object::Integer(num1 + num2))
-                        }
-                        _ => continue,
-                    }
-                }
-                result.push(arr);
-            }
-
-            Some(Object::Array(result))
+            let (first, second) = (
+                integer_matrix(name, first_arr),
+                integer_matrix(name, second_arr),
+            );
+            Some(integer_array(
+                first
+                    .iter()
+                    .zip(&second)
+                    .map(|(row1, row2)| row1.iter().zip(row2).map(|(a, b)| a + b).collect())
+                    .collect::<Vec<Vec<i64>>>(),
+            ))
         }
         ("diff_matrix", [Object::Array(first_arr), Object::Array(second_arr)]) => {
             if !check_array_size(first_arr) || !check_array_size(second_arr) {
@@ -476,39 +1424,148 @@ fn eval_builtin(name: &str, args: Vec<Object>, env: &mut Env) -> Option<Object>
                 panic!("Arrays should to be the same size");
             }
 
-            let mut result = vec![];
-
-            for row in 0..first_arr.len() {
-                let mut arr = vec![];
-                for col in 0..first_arr[0].len() {
-                    match (&first_arr[row][col], &second_arr[row][col]) {
-                        (Object::Integer(num1), Object::Integer(num2)) => {
-                            arr.push(Object::Integer(num1 - num2))
-                        }
-                        _ => (),
-                    }
+            let (first, second) = (
+                integer_matrix(name, first_arr),
+                integer_matrix(name, second_arr),
+            );
+            Some(integer_array(
+                first
+                    .iter()
+                    .zip(&second)
+                    .map(|(row1, row2)| row1.iter().zip(row2).map(|(a, b)| a - b).collect())
+                    .collect::<Vec<Vec<i64>>>(),
+            ))
+        }
+        (name, [Object::Array(els)]) if stats::STATISTICS.contains(&name) => {
+            Some(stats::reduce(name, els, None))
+        }
+        ("sum", [Object::Array(els), Object::Integer(axis)])
+        | ("max", [Object::Array(els), Object::Integer(axis)])
+        | ("min", [Object::Array(els), Object::Integer(axis)]) => {
+            Some(stats::reduce(name, els, Some(*axis)))
+        }
+        (name, [Object::Array(els), Object::Integer(axis)])
+            if stats::STATISTICS.contains(&name) =>
+        {
+            Some(stats::reduce(name, els, Some(*axis)))
+        }
+        ("sum", [Object::List(els)])
+        | ("max", [Object::List(els)])
+        | ("min", [Object::List(els)]) => Some(stats::reduce_list(name, els)),
+        (name, [Object::List(els)]) if stats::STATISTICS.contains(&name) => {
+            Some(stats::reduce_list(name, els))
+        }
+        ("input", []) => Some(input::input(None, env)),
+        ("input", [Object::String(prompt)]) => Some(input::input(Some(prompt), env)),
+        ("read_int", []) => Some(input::read_int(env)),
+        ("read_matrix", [Object::Integer(rows), Object::Integer(cols)]) => Some(
+            input::read_matrix(matrix_size(*rows), matrix_size(*cols), env),
+        ),
+        ("read_file", [Object::String(path)]) => Some(files::read_file(path, env)),
+        ("read_lines", [Object::String(path)]) => Some(files::read_lines(path, env)),
+        ("read_csv", [Object::String(path)]) => Some(files::read_csv(path, env)),
+        ("write_file", [Object::String(path), Object::String(contents)]) => {
+            Some(files::write_file(path, contents, env))
+        }
+        ("write_csv", [Object::String(path), Object::Array(els)]) => {
+            Some(files::write_csv(path, els, env))
+        }
+        ("help", [Object::String(function)]) => match env.native(function) {
+            Some(native) => Some(Object::String(native.doc.clone())),
+            None => panic!("No documentation for {}", function),
+        },
+        ("map", [function, xs]) => {
+            let (items, _) = collection_items(name, xs);
+            Some(Object::List(
+                items
+                    .into_iter()
+                    .map(|item| call_function(function.clone(), vec![item], env))
+                    .collect(),
+            ))
+        }
+        ("filter", [function, xs]) => {
+            let (items, kind) = collection_items(name, xs);
+            let mut kept = vec![];
+            for item in items {
+                let keep = call_function(function.clone(), vec![item.clone()], env);
+                if is_truthy(&keep, env.strict) {
+                    kept.push(item);
                 }
-                result.push(arr);
             }
-            Some(Object::Array(result))
+            Some(from_collection_items(kept, kind))
         }
+        ("reduce", [function, init, xs]) => {
+            let (items, _) = collection_items(name, xs);
+            Some(items.into_iter().fold(init.clone(), |acc, item| {
+                call_function(function.clone(), vec![acc, item], env)
+            }))
+        }
+        ("zip", [xs, ys]) => {
+            let (xs, _) = collection_items(name, xs);
+            let (ys, _) = collection_items(name, ys);
+            Some(Object::List(
+                xs.into_iter()
+                    .zip(ys)
+                    .map(|(x, y)| Object::List(vec![x, y]))
+                    .collect(),
+            ))
+        }
+        ("enumerate", [xs]) => {
+            let (items, _) = collection_items(name, xs);
+            Some(Object::List(
+                items
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, item)| Object::List(vec![Object::Integer(i as i64), item]))
+                    .collect(),
+            ))
+        }
+        ("sort", [xs]) => {
+            let (mut items, kind) = collection_items(name, xs);
+            items.sort_by(compare_objects);
+            Some(from_collection_items(items, ordered(kind)))
+        }
+        ("sort_by", [function, xs]) => {
+            let (items, kind) = collection_items(name, xs);
+            let mut keyed: Vec<(Object, Object)> = items
+                .into_iter()
+                .map(|item| {
+                    (
+                        call_function(function.clone(), vec![item.clone()], env),
+                        item,
+                    )
+                })
+                .collect();
+            keyed.sort_by(|(key1, _), (key2, _)| compare_objects(key1, key2));
+            Some(from_collection_items(
+                keyed.into_iter().map(|(_, item)| item).collect(),
+                ordered(kind),
+            ))
+        }
+        ("reverse", [xs]) => {
+            let (mut items, kind) = collection_items(name, xs);
+            items.reverse();
+            Some(from_collection_items(items, ordered(kind)))
+        }
+        ("det", [Object::Array(els)]) => Some(linalg::det(els)),
+        ("rank", [Object::Array(els)]) => Some(linalg::rank(els)),
+        ("trace", [Object::Array(els)]) => Some(linalg::trace(els)),
+        ("inverse", [Object::Array(els)]) => Some(linalg::inverse(els)),
+        ("solve", [Object::Array(a), b]) => Some(linalg::solve(a, b)),
+        ("lu", [Object::Array(els)]) => Some(linalg::lu(els)),
+        ("qr", [Object::Array(els)]) => Some(linalg::qr(els)),
+        // Deprecated: `get_row` and `get_col` predate slicing and count from
+        // one. They are kept for existing programs as wrappers over
+        // `m[i - 1, :]` and `m[:, j - 1]`, the column laid out as a row.
         ("get_row", [Object::Integer(index), Object::Array(els)]) => {
             if *index as usize > els.len() || *index <= 0 {
                 panic!("Invalid index");
             }
-
-            let mut result = vec![];
-            let row = *index as usize - 1;
-
-            for el in 0..els[row - 1].len() {
-                match els[row][el] {
-                    Object::Integer(int) => {
-                        result.push(Object::Integer(int));
-                    }
-                    _ => (),
-                }
-            }
-            Some(Object::Array(vec![result]))
+            let row = IndexArg::At(Object::Integer(index - 1));
+            Some(select_slice(
+                Object::Array(els.clone()),
+                &[row, IndexArg::Slice(None, None, None)],
+            ))
         }
         ("get_col", [Object::Integer(index), Object::Array(els)]) => {
             if !check_array_size(els) {
@@ -518,25 +1575,113 @@ fn eval_builtin(name: &str, args: Vec<Object>, env: &mut Env) -> Option<Object>
             if *index as usize > els[0].len() || *index <= 0 {
                 panic!("Invalid index");
             }
-
-            let mut result = vec![];
-            let col = *index as usize - 1;
-            for el in 0..els.len() {
-                match els[el][col] {
-                    Object::Integer(int) => {
-                        result.push(Object::Integer(int));
-                    }
-                    _ => (),
-                }
+            let col = IndexArg::At(Object::Integer(index - 1));
+            match select_slice(
+                Object::Array(els.clone()),
+                &[IndexArg::Slice(None, None, None), col],
+            ) {
+                Object::Array(column) => Some(Object::Array(vec![column.concat()])),
+                _ => unreachable!(),
             }
-            Some(Object::Array(vec![result]))
         }
         _ => {
+            // The matrix builtins also take rank 2 tensors, which are passed
+            // to them in the matrix form.
+            if args
+                .iter()
+                .any(|arg| matches!(arg, Object::Tensor(shape, _) if shape.len() == 2))
+            {
+                let args = args
+                    .iter()
+                    .map(|arg| match arg {
+                        Object::Tensor(shape, data) if shape.len() == 2 => {
+                            Object::Array(tensor::to_matrix(shape, data))
+                        }
+                        other => other.clone(),
+                    })
+                    .collect();
+                return eval_builtin(name, args, env);
+            }
             //    panic!(format!("Unrecognizable function {:?}", name).as_str()),
             None
         }
     }
 }
+fn matrix_size(num: i64) -> usize {
+    if num < 0 {
+        panic!("Matrix dimensions must be non-negative, got {}", num);
+    }
+    num as usize
+}
+
+fn filled_matrix(rows: i64, cols: i64, value: Object) -> Object {
+    Object::Array(vec![vec![value; matrix_size(cols)]; matrix_size(rows)])
+}
+
+/// `zeros`, `ones` and `fill` for a shape list. Two dimensions give a
+/// matrix, the same as `zeros(r, c)`; any other number gives a tensor.
+fn filled_shape(shape: &Object, value: Object) -> Object {
+    match tensor::shape_from_object(shape).as_slice() {
+        &[rows, cols] => Object::Array(vec![vec![value; cols]; rows]),
+        _ => tensor::filled(tensor::shape_from_object(shape), value),
+    }
+}
+
+/// The numbers from `start` up to, but excluding, `stop` in increments of
+/// `step`, which may be negative. Integers stay integers unless any of the
+/// three arguments is a float.
+fn arange(start: &Object, stop: &Object, step: &Object) -> Object {
+    match (start, stop, step) {
+        (_, _, Object::Integer(0)) => panic!("arange step must not be zero"),
+        (Object::Integer(start), Object::Integer(stop), Object::Integer(step)) => {
+            let mut els = vec![];
+            let mut num = *start;
+            while (*step > 0 && num < *stop) || (*step < 0 && num > *stop) {
+                els.push(Object::Integer(num));
+                num += step;
+            }
+            Object::List(els)
+        }
+        _ => {
+            let (start, stop, step) = (number_value(start), number_value(stop), number_value(step));
+            if step == 0.0 {
+                panic!("arange step must not be zero");
+            }
+            let count = ((stop - start) / step).ceil().max(0.0) as usize;
+            Object::List(
+                (0..count)
+                    .map(|i| Object::Float(start + i as f64 * step))
+                    .collect(),
+            )
+        }
+    }
+}
+
+fn shift_amount(num: i64) -> u32 {
+    if num < 0 || num >= 64 {
+        panic!("Shift amount must be between 0 and 63, got {}", num);
+    }
+    num as u32
+}
+
+/// The matrix argument of an integer-only builtin such as `add_matrix`.
+fn integer_matrix(name: &str, els: &[Vec<Object>]) -> Vec<Vec<i64>> {
+    match Vec::<Vec<i64>>::try_from(Object::Array(els.to_vec())) {
+        Ok(m) => m,
+        Err(Error::Conversion(msg)) => panic!("{} expects a matrix of integers, {}", name, msg),
+        Err(err) => panic!("{}", err),
+    }
+}
+
+/// The result of an integer-only builtin, whose rows all have one length.
+fn integer_array(rows: Vec<Vec<i64>>) -> Object {
+    Object::Array(
+        rows.into_iter()
+            .map(|row| row.into_iter().map(Object::Integer).collect())
+            .collect(),
+    )
+}
+
 fn check_array_size(arr: &Vec<Vec<Object>>) -> bool {
     for index in 0..arr.len() - 1 {
         if (arr[index].len() != arr[index + 1].len()) {
@@ -550,10 +1695,14 @@ fn eval_statement(statement: Statement, env: &mut Env) -> Object {
         Statement::Expression(expr) => eval_expr(expr, env),
         Statement::Let(ident, val) => {
             let _val = eval_expr(val, env);
-            env.set(ident, _val.clone());
+            env.declare(ident, _val.clone());
             _val
         }
         Statement::Return(expr) => Object::Return(Box::new(eval_expr(expr, env))),
+        Statement::Struct(name, fields) => {
+            env.structs.insert(name, fields);
+            Object::Null
+        }
         _ => panic!("Unidentified statement"),
     }
 }
@@ -570,7 +1719,7 @@ pub fn eval_statements(stmnts: Vec<Statement>, env: &mut Env) -> Object {
     result
 }
 
-fn eval_return(stmnts: Vec<Statement>, env: &mut Env) -> Object {
+pub fn eval_return(stmnts: Vec<Statement>, env: &mut Env) -> Object {
     let result = eval_statements(stmnts, env);
     match result {
         Object::Return(val) => *val,
@@ -581,6 +1730,7 @@ fn eval_return(stmnts: Vec<Statement>, env: &mut Env) -> Object {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::evaluation_mod::native::{NativeFunction, ParamType};
     use crate::lexer_mod::lexer::Lexer;
     use crate::parser_mod::Parser::Parser;
 
@@ -606,75 +1756,1722 @@ mod tests {
     }
 
     #[test]
-    fn test_bools() {
-        eval("false;", Object::Boolean(false));
-        eval("true;", Object::Boolean(true));
-        eval("!false;", Object::Boolean(true));
-        eval("!true;", Object::Boolean(false));
+    fn test_operators() {
+        eval("17 % 5;", Object::Integer(2));
+        eval("-17 % 5;", Object::Integer(-2));
+        eval("2 ** 10;", Object::Integer(1024));
+        eval("2 ** 3 ** 2;", Object::Integer(512));
+        eval("-2 ** 2;", Object::Integer(-4));
+        eval("2 * 3 ** 2;", Object::Integer(18));
+        eval("12 & 10;", Object::Integer(8));
+        eval("12 | 10;", Object::Integer(14));
+        eval("12 ^ 10;", Object::Integer(6));
+        eval("~5;", Object::Integer(-6));
+        eval("1 << 4;", Object::Integer(16));
+        eval("-16 >> 2;", Object::Integer(-4));
+        eval("1 + 2 << 3;", Object::Integer(24));
+        eval("1 | 2 ^ 3 & 6;", Object::Integer(1));
+        eval("6 & 3 == 2;", Object::Boolean(true));
     }
+
     #[test]
-    fn test_arrays() {
-        let one = Object::Integer(1);
-        let two = Object::Integer(2);
-        let three = Object::Integer(3);
-        let four = Object::Integer(4);
-        let five = Object::Integer(5);
+    fn test_assignment() {
+        eval("let x = 5; x += 3; x;", Object::Integer(8));
+        eval("let x = 5; x -= 3; x;", Object::Integer(2));
+        eval("let x = 5; x *= 3; x;", Object::Integer(15));
+        eval("let x = 15; x /= 4; x;", Object::Integer(3));
+        eval("let x = 15; x %= 4; x;", Object::Integer(3));
         eval(
-            "[{1, 2, 3, 4, 5}];",
-            Object::Array([[one, two, three, four, five].to_vec()].to_vec()),
+            "let x = 1; let y = 2; x = y = 7; x + y;",
+            Object::Integer(14),
         );
+        eval("let x = 1; x = 2 + 3; x;", Object::Integer(5));
+        eval("let x = 5; +x;", Object::Integer(5));
+    }
 
-        let one = Object::Integer(1);
-        let two = Object::Integer(2);
-        let three = Object::Integer(3);
-        let four = Object::Integer(4);
-        let five = Object::Integer(5);
-        eval(
-            "[{1+1-1, 2+2-2, 3+1-1, 4+0, 5/1} {1, 2, 3, 4, 5}];",
-            Object::Array(
-                [
-                    [one, two, three, four, five].to_vec(),
-                    [
-                        Object::Integer(1),
-                        Object::Integer(2),
-                        Object::Integer(3),
-                        Object::Integer(4),
-                        Object::Integer(5),
-                    ]
-                    .to_vec(),
-                ]
-                .to_vec(),
+    #[test]
+    fn test_increment_decrement() {
+        eval("let x = 5; ++x;", Object::Integer(6));
+        eval("let x = 5; --x;", Object::Integer(4));
+        eval("let x = 5; x++;", Object::Integer(5));
+        eval("let x = 5; x++; x;", Object::Integer(6));
+        eval("let x = 5; x--; x;", Object::Integer(4));
+        eval("let x = 5; x++ + ++x;", Object::Integer(12));
+    }
+
+    #[test]
+    fn test_update_evaluates_indices_once() {
+        let mut env = Env::new();
+        env.set_input(io::Cursor::new("1\n0\n2\n7\n"));
+        assert_eq!(
+            eval_in(
+                "let m = [{1, 2} {3, 4}]; m[read_int(), read_int()] += 5; m;",
+                &mut env
             ),
+            matrix(vec![vec![1, 2], vec![8, 4]])
+        );
+        assert_eq!(
+            eval_in("let l = [10, 20, 30]; l[read_int()]++; l;", &mut env),
+            ints(vec![10, 20, 31])
         );
+        assert_eq!(eval_in("read_int();", &mut env), Object::Integer(7));
     }
 
     #[test]
-    fn test_built_in() {
-        eval("size([{1, 2, 3, 4, 5}]);", Object::Integer(5));
+    fn test_matrix_cell_assignment() {
+        eval("let m = [{1, 2} {3, 4}]; m[1, 0];", Object::Integer(3));
         eval(
-            "size([{1, 2, 3, 4, 5} {10, 12, 123, 155, 166} {100, 99, 98, 97, 96}]);",
-            Object::Integer(15),
+            "let m = [{1, 2} {3, 4}]; m[1];",
+            Object::Array(vec![vec![Object::Integer(3), Object::Integer(4)]]),
         );
         eval(
-            "max([{1, 2, 3, 4, 5} {10, 12, 123, 155, 166} {100, 99, 98, 97, 96}]);",
-            Object::Integer(166),
+            "let m = [{1, 2} {3, 4}]; m[1, 0] += 10; m;",
+            Object::Array(vec![
+                vec![Object::Integer(1), Object::Integer(2)],
+                vec![Object::Integer(13), Object::Integer(4)],
+            ]),
         );
         eval(
-            "min([{1, 2, 3, 4, 5} {10, 12, 123, 155, 166} {100, 99, 98, 97, 96}]);",
-            Object::Integer(1),
+            "let m = [{1, 2} {3, 4}]; m[0, 1]++; --m[1, 1]; m;",
+            Object::Array(vec![
+                vec![Object::Integer(1), Object::Integer(3)],
+                vec![Object::Integer(3), Object::Integer(3)],
+            ]),
         );
         eval(
-            "sum([{1, 2, 3, 4, 5} {3, 4, 1, 2, 0} {10, 10, 10, 10, 10} ]);",
-            Object::Integer(75),
+            "let m = [{1, 2, 3}]; m[0, 2] = 9; m;",
+            Object::Array(vec![vec![
+                Object::Integer(1),
+                Object::Integer(2),
+                Object::Integer(9),
+            ]]),
         );
         eval(
-            "constant_product([{1, 2}], 10);",
-            Object::Array([[Object::Integer(10), Object::Integer(20)].to_vec()].to_vec()),
+            "let m = [{1, 2} {3, 4}]; m[0] = [{5, 6}]; m[1] = 0; m;",
+            Object::Array(vec![
+                vec![Object::Integer(5), Object::Integer(6)],
+                vec![Object::Integer(0), Object::Integer(0)],
+            ]),
+        );
+    }
+
+    #[test]
+    fn test_if_expressions() {
+        eval("if (1 < 2) { 10 } else { 20 };", Object::Integer(10));
+        eval("if (1 > 2) { 10 } else { 20 };", Object::Integer(20));
+        eval("if (1 > 2) { 10 };", Object::Null);
+        eval("if (true) { 1; 2; 3 };", Object::Integer(3));
+        eval(
+            "let x = if (false) { 1 } else { 2 }; x;",
+            Object::Integer(2),
+        );
+        eval(
+            "let n = 5; if (n < 3) { 1 } else if (n < 6) { 2 } else { 3 };",
+            Object::Integer(2),
+        );
+        eval(
+            "let n = 9; if (n < 3) { 1 } else if (n < 6) { 2 } else { 3 };",
+            Object::Integer(3),
+        );
+        eval(
+            "let n = 9; if (n < 3) { 1 } else if (n < 6) { 2 };",
+            Object::Null,
+        );
+        eval(
+            "let x = 1; if (true) { x += 1; x += 1; }; x;",
+            Object::Integer(3),
+        );
+    }
+
+    #[test]
+    fn test_truthiness() {
+        eval("if (1) { 10 } else { 20 };", Object::Integer(10));
+        eval("if (0) { 10 } else { 20 };", Object::Integer(20));
+        eval("if (-3) { 10 } else { 20 };", Object::Integer(10));
+        eval("if ([{1}]) { 10 } else { 20 };", Object::Integer(10));
+        eval("if ([]) { 10 } else { 20 };", Object::Integer(20));
+        eval("if ([{}]) { 10 } else { 20 };", Object::Integer(20));
+        eval(
+            "let x = 4; if (x % 2) { 1 } else { 0 };",
+            Object::Integer(0),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Condition must be a boolean in strict mode")]
+    fn test_strict_conditions() {
+        let lexer = Lexer::new("if (1) { 10 } else { 20 };");
+        let mut parser = Parser::new(lexer);
+        let mut env = Env::new();
+        env.strict = true;
+        eval_return(parser.parse(), &mut env);
+    }
+
+    #[test]
+    fn test_blocks() {
+        eval("{ 1; 2; 3 };", Object::Integer(3));
+        eval("let x = { let y = 2; y * y }; x;", Object::Integer(4));
+        eval("let x = { let y = 2; y * y; }; x;", Object::Integer(4));
+        eval("{ let y = 2; { y + 1 } }", Object::Integer(3));
+        eval("(1 + 2) * 3", Object::Integer(9));
+        eval("let x = 1; x += 2", Object::Integer(3));
+        eval(";; 5;;", Object::Integer(5));
+    }
+
+    #[test]
+    fn test_block_scope() {
+        eval("let x = 1; { let x = 2; x }; x", Object::Integer(1));
+        eval("let x = 1; { x = 2; }; x", Object::Integer(2));
+        eval(
+            "let x = 1; { let y = 5; x = y + 1; }; x",
+            Object::Integer(6),
+        );
+        eval("let x = 1; { let x = 2; x = 3; }; x", Object::Integer(1));
+        eval("let x = 1; { let x = 2; { x = 3; } x }", Object::Integer(3));
+        eval("{ let x = 1; { let x = 2; } x }", Object::Integer(1));
+        eval("let x = 0; if (true) { x = 5; } x", Object::Integer(5));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_block_let_does_not_leak() {
+        eval("{ let y = 2; }; y", Object::Integer(2));
+    }
+
+    #[test]
+    fn test_statement_terminators() {
+        eval("if (true) { 1 } 2", Object::Integer(2));
+        eval(
+            "if (true) { 1 } else { 2 } let x = 3; x",
+            Object::Integer(3),
+        );
+        eval("{ 1 } { 2 }", Object::Integer(2));
+        eval(
+            "let f = fn f(a) { let b = a * 2; if (b > 4) { b } else { 0 } }; f(3)",
+            Object::Integer(6),
+        );
+        eval(
+            "let f = fn f(a) { let b = a * 2; return b + 1; }; f(3);",
+            Object::Integer(7),
+        );
+        eval("let f = fn f() { 42 }; f()", Object::Integer(42));
+    }
+
+    #[test]
+    fn test_llvm_backend_rejects_cell_updates() {
+        let errors = |input: &str| {
+            let mut parser = Parser::for_llvm_backend(Lexer::new(input));
+            parser.parse();
+            parser
+                .get_errors()
+                .iter()
+                .map(|err| err.to_string())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(
+            errors("m[i, j] += 1;"),
+            vec![
+                "Invalid Assignment Target!: the LLVM backend can only update variables, \
+                 not Identifier(\"m\")[Identifier(\"i\"), Identifier(\"j\")]"
+                    .to_string()
+            ]
+        );
+        assert_eq!(errors("m[i, j]++;").len(), 1);
+        assert_eq!(errors("++p.x;").len(), 1);
+        assert!(errors("let x = 1; x += 1; x++; --x; m[0, 0] = 1;").is_empty());
+        assert!(parse_errors("m[i, j] += 1; m[i, j]++;").is_empty());
+    }
+
+    fn parse_errors(input: &str) -> Vec<String> {
+        let mut parser = Parser::new(Lexer::new(input));
+        parser.parse();
+        parser
+            .get_errors()
+            .iter()
+            .map(|err| err.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_missing_terminators() {
+        let expected = vec!["Unexpected Token!: expected ;, but found Let instead".to_string()];
+        assert_eq!(parse_errors("let x = 1 let y = 2;"), expected);
+        assert_eq!(parse_errors("{ let x = 1 let y = 2; }"), expected);
+        assert_eq!(
+            parse_errors("fn f() { 1 2 }"),
+            vec!["Unexpected Token!: expected ;, but found Int(2) instead".to_string()]
+        );
+        assert_eq!(
+            parse_errors("{ 1"),
+            vec!["Unexpected End of Input!: expected }, but found Eof instead".to_string()]
+        );
+        assert!(parse_errors("let x = 1; { x }; if (x) { 2 } x").is_empty());
+    }
+
+    #[test]
+    fn test_match_expressions() {
+        let input = "let f = fn f(x) { match (x) { 1 => 10, 2 | 3 => 20, -1 => 30, _ => 40 } };";
+        eval(&format!("{} f(1)", input), Object::Integer(10));
+        eval(&format!("{} f(3)", input), Object::Integer(20));
+        eval(&format!("{} f(-1)", input), Object::Integer(30));
+        eval(&format!("{} f(7)", input), Object::Integer(40));
+        eval("match (7) { 1 => 0, n => n * 2 }", Object::Integer(14));
+        eval(
+            "match (1 < 2) { true => 1, false => 0 }",
+            Object::Integer(1),
+        );
+        eval(
+            "match (2) { 1 => { let y = 5; y } _ => { let y = 6; y } }",
+            Object::Integer(6),
+        );
+        eval(
+            "let x = match (3) { 3 => 1, _ => 2 }; x + 1;",
+            Object::Integer(2),
+        );
+    }
+
+    #[test]
+    fn test_match_binding_scope() {
+        eval("let n = 1; match (7) { n => n * 2 }; n", Object::Integer(1));
+        eval(
+            "let total = 0; match (7) { n => { total = n; } }; total",
+            Object::Integer(7),
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_match_binding_does_not_leak() {
+        eval("match (7) { n => n }; n", Object::Integer(7));
+    }
+
+    #[test]
+    fn test_match_array_patterns() {
+        let input = "let f = fn f(m) {
+            match (m) {
+                [{a, b}] => a + b,
+                [{1, _} {_, d}] => d,
+                [{a, b} {c, d}] => a * d - b * c,
+                [] => -1,
+                _ => 0
+            }
+        };";
+        eval(&format!("{} f([{{1, 2}}])", input), Object::Integer(3));
+        eval(
+            &format!("{} f([{{1, 2}} {{3, 4}}])", input),
+            Object::Integer(4),
+        );
+        eval(
+            &format!("{} f([{{2, 2}} {{3, 4}}])", input),
+            Object::Integer(2),
+        );
+        eval(&format!("{} f([])", input), Object::Integer(-1));
+        eval(&format!("{} f([{{1, 2, 3}}])", input), Object::Integer(0));
+        eval(&format!("{} f(5)", input), Object::Integer(0));
+    }
+
+    #[test]
+    fn test_match_exhaustiveness() {
+        let expected =
+            vec!["Non-exhaustive Match!: add a `_` arm to cover the remaining values".to_string()];
+        assert_eq!(parse_errors("match (1) { 1 => 2, 2 => 3 }"), expected);
+        assert_eq!(parse_errors("match (true) { true => 2 }"), expected);
+        assert_eq!(parse_errors("match ([{1}]) { [{a}] => a }"), expected);
+        assert!(parse_errors("match (true) { true => 1, false => 2 }").is_empty());
+        assert!(parse_errors("match (1) { 1 => 1, x => x }").is_empty());
+    }
+
+    #[test]
+    fn test_match_alternatives_bind_the_same_names() {
+        assert_eq!(
+            parse_errors("match ([{1, 2}]) { [{a, _}] | [{_, b}] => a + b, _ => 0 }"),
+            vec![
+                "Mismatched Bindings!: a is not bound by every alternative of [{a, _}] | [{_, b}]"
+                    .to_string()
+            ]
+        );
+        eval(
+            "match ([{1, 2}]) { [{a, 1}] | [{1, a}] => a, _ => 0 }",
+            Object::Integer(2),
+        );
+    }
+
+    fn map(pairs: Vec<(MapKey, Object)>) -> Object {
+        Object::Map(pairs.into_iter().collect())
+    }
+
+    #[test]
+    fn test_maps() {
+        eval(
+            "let m = {\"rows\": 3, \"cols\": 4}; m[\"rows\"] * m[\"cols\"];",
+            Object::Integer(12),
+        );
+        eval(
+            "{1: true, true: 2, \"a\": [{1}]};",
+            map(vec![
+                (MapKey::Integer(1), Object::Boolean(true)),
+                (MapKey::Boolean(true), Object::Integer(2)),
+                (
+                    MapKey::String("a".to_string()),
+                    Object::Array(vec![vec![Object::Integer(1)]]),
+                ),
+            ]),
+        );
+        eval("let m = {}; m;", map(vec![]));
+        eval(
+            "let k = 2; {-1: 1, k * 2: 4, \"a\": k}[-1];",
+            Object::Integer(1),
+        );
+        eval(
+            "{-1: 2, 1 + 1: 3};",
+            map(vec![
+                (MapKey::Integer(-1), Object::Integer(2)),
+                (MapKey::Integer(2), Object::Integer(3)),
+            ]),
+        );
+        eval("let x = 1; { -x }", Object::Integer(-1));
+        eval("let x = 1; { x = 2; x }", Object::Integer(2));
+        eval("{};", Object::Null);
+        eval("let x = 1; {} x", Object::Integer(1));
+        eval("fn f() { {} } f();", Object::Null);
+        eval(
+            "let k = \"x\"; let m = {k: 1 + 1}; m[\"x\"];",
+            Object::Integer(2),
+        );
+        eval(
+            "let m = {}; m[\"a\"] = 5; m[\"a\"] += 1; m[\"a\"];",
+            Object::Integer(6),
+        );
+        eval(
+            "let m = {\"inner\": {\"x\": 1}}; m[\"inner\"][\"x\"] = 7; m[\"inner\"][\"x\"];",
+            Object::Integer(7),
+        );
+        eval(
+            "let m = {\"m\": [{1, 2}]}; m[\"m\"][0, 1] = 9; m[\"m\"];",
+            Object::Array(vec![vec![Object::Integer(1), Object::Integer(9)]]),
+        );
+        eval(
+            "let dims = fn dims(m) { {\"rows\": 3, \"cols\": 4} }; dims(0)[\"cols\"];",
+            Object::Integer(4),
+        );
+        eval("if ({}) { 1 } else { 2 }", Object::Integer(2));
+        eval("\"a\" == \"a\";", Object::Boolean(true));
+    }
+
+    #[test]
+    fn test_map_builtins() {
+        let input = "let m = {\"b\": 2, \"a\": 1};";
+        eval(&format!("{} size(m);", input), Object::Integer(2));
+        eval(
+            &format!("{} keys(m);", input),
+            Object::List(vec![
+                Object::String("a".to_string()),
+                Object::String("b".to_string()),
+            ]),
+        );
+        eval(
+            &format!("{} values(m);", input),
+            Object::List(vec![Object::Integer(1), Object::Integer(2)]),
+        );
+        eval(
+            &format!("{} has_key(m, \"a\");", input),
+            Object::Boolean(true),
+        );
+        eval(
+            &format!("{} m = remove(m, \"a\"); has_key(m, \"a\");", input),
+            Object::Boolean(false),
+        );
+        eval(
+            &format!("{} remove(m, \"a\");", input),
+            map(vec![(MapKey::String("b".to_string()), Object::Integer(2))]),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Key String(\"c\") not found")]
+    fn test_missing_map_key() {
+        eval("let m = {\"a\": 1}; m[\"c\"];", Object::Null);
+    }
+
+    fn point(x: i64, y: i64) -> Object {
+        Object::Struct(
+            "Point".to_string(),
+            vec![
+                ("x".to_string(), Object::Integer(x)),
+                ("y".to_string(), Object::Integer(y)),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_structs() {
+        let input = "struct Point { x, y }";
+        eval(
+            &format!("{} let p = Point {{ x: 1, y: 2 }}; p.x + p.y", input),
+            Object::Integer(3),
+        );
+        eval(&format!("{} Point {{ y: 2, x: 1 }}", input), point(1, 2));
+        eval(
+            &format!(
+                "{} let p = Point {{ x: 1, y: 2 }}; p.x = 10; p.y += 1; p",
+                input
+            ),
+            point(10, 3),
+        );
+        eval(
+            &format!(
+                "{} struct Line {{ a, b }}
+                let l = Line {{ a: Point {{ x: 1, y: 2 }}, b: Point {{ x: 3, y: 4 }} }};
+                l.b.y = 7;
+                l.a.x + l.b.y",
+                input
+            ),
+            Object::Integer(8),
+        );
+        eval(
+            &format!(
+                "{} let make = fn make(a) {{ Point {{ x: a, y: a * 2 }} }}; make(3)",
+                input
+            ),
+            point(3, 6),
+        );
+        eval(
+            &format!(
+                "{} let m = {{\"p\": Point {{ x: 1, y: 2 }}}}; m[\"p\"].y",
+                input
+            ),
+            Object::Integer(2),
+        );
+        assert_eq!(point(1, 2).to_string(), "Point { x: 1, y: 2 }");
+    }
+
+    #[test]
+    #[should_panic(expected = "Missing field y in Point literal")]
+    fn test_struct_missing_field() {
+        eval("struct Point { x, y } Point { x: 1 }", Object::Null);
+    }
+
+    #[test]
+    #[should_panic(expected = "Duplicate field x in Point")]
+    fn test_struct_duplicate_field() {
+        eval(
+            "struct Point { x, y } Point { x: 1, x: 2, y: 3 }",
+            Object::Null,
+        );
+    }
+
+    #[test]
+    fn test_struct_declaration_duplicate_field() {
+        assert_eq!(
+            parse_errors("struct Point { x, y, x }"),
+            vec!["Duplicate Field!: field x is declared twice in struct Point".to_string()]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Struct Point has no field z")]
+    fn test_struct_unknown_field() {
+        eval(
+            "struct Point { x, y } let p = Point { x: 1, y: 2 }; p.z",
+            Object::Null,
+        );
+    }
+
+    fn list(els: Vec<Object>) -> Object {
+        Object::List(els)
+    }
+
+    #[test]
+    fn test_lists() {
+        eval(
+            "[1, true, \"a\"];",
+            list(vec![
+                Object::Integer(1),
+                Object::Boolean(true),
+                Object::String("a".to_string()),
+            ]),
+        );
+        eval("[];", list(vec![]));
+        eval(
+            "[[1, 2], [], [[3]]];",
+            list(vec![
+                list(vec![Object::Integer(1), Object::Integer(2)]),
+                list(vec![]),
+                list(vec![list(vec![Object::Integer(3)])]),
+            ]),
+        );
+        eval(
+            "[{\"a\": 1}];",
+            list(vec![Object::Map(
+                vec![(MapKey::String("a".to_string()), Object::Integer(1))]
+                    .into_iter()
+                    .collect(),
+            )]),
+        );
+        eval(
+            "[[{1, 2}], [1, 2]][0];",
+            Object::Array(vec![vec![Object::Integer(1), Object::Integer(2)]]),
+        );
+        eval("let xs = [[1, 2], [3, 4]]; xs[1][0];", Object::Integer(3));
+        eval(
+            "let xs = [[1, 2], [3, 4]]; xs[1][0] = 9; xs[0] = 5; xs;",
+            list(vec![
+                Object::Integer(5),
+                list(vec![Object::Integer(9), Object::Integer(4)]),
+            ]),
+        );
+        eval("if ([]) { 1 } else { 2 }", Object::Integer(2));
+        eval("if ([0]) { 1 } else { 2 }", Object::Integer(1));
+        eval(
+            "match ([1, [2, 3]]) { [a, [b, c]] => a + b + c, _ => 0 }",
+            Object::Integer(6),
+        );
+        assert_eq!(
+            list(vec![
+                Object::Integer(1),
+                list(vec![Object::String("a".to_string())])
+            ])
+            .to_string(),
+            "[1, [\"a\"]]"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot pop from an empty list")]
+    fn test_pop_empty_list() {
+        eval("pop([]);", Object::Null);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot take the last element of an empty list")]
+    fn test_last_empty_list() {
+        eval("last([]);", Object::Null);
+    }
+
+    #[test]
+    fn test_list_builtins() {
+        eval("len([1, [2, 3], 4]);", Object::Integer(3));
+        eval("size([]);", Object::Integer(0));
+        eval(
+            "push([1], [2]);",
+            list(vec![Object::Integer(1), list(vec![Object::Integer(2)])]),
+        );
+        eval("pop([1, 2, 3]);", ints(vec![1, 2]));
+        eval("pop([[4]]);", list(vec![]));
+        eval("last([1, 2, 3]);", Object::Integer(3));
+        eval("last([[4]]);", ints(vec![4]));
+        eval(
+            "let xs = [1, 2, 3]; let top = last(xs); xs = pop(xs); top * 10 + len(xs);",
+            Object::Integer(32),
+        );
+        eval(
+            "concat([1], [true, 3]);",
+            list(vec![
+                Object::Integer(1),
+                Object::Boolean(true),
+                Object::Integer(3),
+            ]),
+        );
+        eval(
+            "slice([1, 2, 3, 4], 1, 3);",
+            list(vec![Object::Integer(2), Object::Integer(3)]),
+        );
+        eval("slice([1, 2], 2, 2);", list(vec![]));
+        eval(
+            "print([{1, true} {[2], 3}]);",
+            Object::Array(vec![
+                vec![Object::Integer(1), Object::Boolean(true)],
+                vec![list(vec![Object::Integer(2)]), Object::Integer(3)],
+            ]),
+        );
+        eval("size([{1, true} {[2], 3}]);", Object::Integer(4));
+    }
+
+    #[test]
+    fn test_printing() {
+        eval("println(1, \"a\", [2]);", Object::Null);
+        eval("print(\"a\");", Object::String("a".to_string()));
+        eval(
+            "format(\"{} x {} = {}\", 2, 3.5, \"seven\");",
+            Object::String("2 x 3.5 = seven".to_string()),
+        );
+        eval(
+            "format(\"{{}} {}\", [1, \"b\"]);",
+            Object::String("{} [1, \"b\"]".to_string()),
+        );
+        eval(
+            "format(\"m =\\n{}\", [{1, -20} {300, 4}]);",
+            Object::String("m =\n[  1  -20]\n[300    4]".to_string()),
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let mut env = Env::new();
+        let value = eval_in(
+            "fn add(a, b) { a + b }; [1, 2.5, \"a \\\"q\\\"\\n\", true, [{1, 2} {3, 4}], {\"k\": [1]}, add];",
+            &mut env,
+        );
+        assert_eq!(
+            value.to_string(),
+            "[1, 2.5, \"a \\\"q\\\"\\n\", true, [{1, 2} {3, 4}], {\"k\": [1]}, <fn add(a, b)>]"
+        );
+        assert_eq!(Object::String("a \"b\"".to_string()).to_string(), "a \"b\"");
+        for source in &[
+            "[{1, -2.5} {\"x\", false}]",
+            "{1: \"one\", \"two\": [2, [2.0]]}",
+            "{-1: [-1], 0: {-2: false}}",
+            "[\"tab\\t\", \"back\\\\slash\"]",
+        ] {
+            let value = eval_in(&format!("{};", source), &mut env);
+            let reparsed = eval_in(&format!("{};", value), &mut env);
+            assert_eq!(value, reparsed);
+        }
+    }
+
+    #[test]
+    fn test_input_builtins() {
+        let mut env = Env::new();
+        env.set_input(io::Cursor::new("Ada\r\n2 3\n1, 2 3\n4 5.5\n6\n"));
+        assert_eq!(
+            eval_in("input();", &mut env),
+            Object::String("Ada".to_string())
+        );
+        assert_eq!(
+            eval_in("let dims = input(); read_matrix(2, 3);", &mut env),
+            Object::Array(vec![
+                vec![Object::Integer(1), Object::Integer(2), Object::Integer(3)],
+                vec![Object::Integer(4), Object::Float(5.5), Object::Integer(6)],
+            ])
+        );
+        assert_eq!(
+            eval_in("dims;", &mut env),
+            Object::String("2 3".to_string())
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "read_int: end of input")]
+    fn test_read_int_end_of_input() {
+        let mut env = Env::new();
+        env.set_input(io::Cursor::new("7\n"));
+        assert_eq!(eval_in("read_int();", &mut env), Object::Integer(7));
+        eval_in("read_int();", &mut env);
+    }
+
+    #[test]
+    fn test_assertions() {
+        eval(
+            "assert(1 < 2); assert(true, \"fine\"); assert_eq([1, \"a\"], [1, \"a\"]);",
+            Object::Null,
+        );
+        eval(
+            "assert_matrix_close(inverse([{2, 0} {0, 4}]), [{0.5, 0} {0, 0.25}], 0.000001);",
+            Object::Null,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Assertion failed at line 2, column 3: [1, \"a\"] != [1, \"b\"]")]
+    fn test_assert_eq_failure() {
+        eval(
+            "let x = 1;\n  assert_eq([x, \"a\"], [1, \"b\"]);",
+            Object::Null,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Assertion failed at line 1, column 9: x must be positive")]
+    fn test_assert_failure_message() {
+        eval(
+            "let x = assert(-1 > 0, \"x must be positive\");",
+            Object::Null,
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Assertion failed at line 1, column 1: elements at position 1 differ by more than 0.01: 2 and 2.5"
+    )]
+    fn test_assert_matrix_close_failure() {
+        eval(
+            "assert_matrix_close([{1, 2}], [{1, 2.5}], 0.01);",
+            Object::Null,
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Assertion failed at line 1, column 1: elements at position 0 differ by more than 0.1: NaN and 1.0"
+    )]
+    fn test_assert_matrix_close_nan() {
+        eval(
+            "assert_matrix_close([{0.0 / 0.0}], [{1.0}], 0.1);",
+            Object::Null,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Too few arguments for format string \"{} {}\"")]
+    fn test_format_too_few_arguments() {
+        eval("format(\"{} {}\", 1);", Object::Null);
+    }
+
+    #[test]
+    #[should_panic(expected = "List index 3 out of range for length 3")]
+    fn test_list_index_out_of_range() {
+        eval("[1, 2, 3][3];", Object::Null);
+    }
+
+    fn tensor(shape: Vec<usize>, data: Vec<i64>) -> Object {
+        Object::Tensor(shape, data.into_iter().map(Object::Integer).collect())
+    }
+
+    #[test]
+    fn test_tensors() {
+        eval("zeros([2, 1, 2]);", tensor(vec![2, 1, 2], vec![0, 0, 0, 0]));
+        eval("ones([3]);", tensor(vec![3], vec![1, 1, 1]));
+        eval(
+            "fill([2], true);",
+            Object::Tensor(vec![2], vec![Object::Boolean(true), Object::Boolean(true)]),
+        );
+        eval(
+            "shape(zeros([2, 3, 4]));",
+            list(vec![
+                Object::Integer(2),
+                Object::Integer(3),
+                Object::Integer(4),
+            ]),
+        );
+        eval(
+            "shape([{1, 2, 3} {4, 5, 6}]);",
+            list(vec![Object::Integer(2), Object::Integer(3)]),
+        );
+        eval(
+            "reshape([1, 2, 3, 4, 5, 6], [3, 2]);",
+            tensor(vec![3, 2], vec![1, 2, 3, 4, 5, 6]),
+        );
+        eval(
+            "reshape([{1, 2} {3, 4}], [4]);",
+            tensor(vec![4], vec![1, 2, 3, 4]),
+        );
+
+        let t = "let t = reshape([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11], [2, 3, 2]);";
+        eval(&format!("{} t[1, 2, 0];", t), Object::Integer(10));
+        eval(&format!("{} t[0, 1];", t), tensor(vec![2], vec![2, 3]));
+        eval(
+            &format!("{} t[1];", t),
+            tensor(vec![3, 2], vec![6, 7, 8, 9, 10, 11]),
+        );
+        eval(&format!("{} t[1][2][1];", t), Object::Integer(11));
+        eval(
+            &format!("{} t[0, 2, 1] = 50; t[1, 0] = reshape([8, 9], [2]); t;", t),
+            tensor(vec![2, 3, 2], vec![0, 1, 2, 3, 4, 50, 8, 9, 8, 9, 10, 11]),
+        );
+        eval(
+            &format!("{} t[1, 1, 1] += 2; t[1, 1, 1];", t),
+            Object::Integer(11),
+        );
+        eval(&format!("{} size(t);", t), Object::Integer(12));
+        eval(&format!("{} sum(t);", t), Object::Integer(66));
+        eval(
+            "transpose_matrix(reshape([1, 2, 3, 4, 5, 6], [2, 3]));",
+            Object::Array(vec![
+                vec![Object::Integer(1), Object::Integer(4)],
+                vec![Object::Integer(2), Object::Integer(5)],
+                vec![Object::Integer(3), Object::Integer(6)],
+            ]),
+        );
+        assert_eq!(
+            tensor(vec![2, 2], vec![1, 2, 3, 4]).to_string(),
+            "[[1, 2], [3, 4]]"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Tensor index 3 out of range for dimension 1 of size 3")]
+    fn test_tensor_index_out_of_range() {
+        eval("zeros([2, 3, 1])[1, 3];", Object::Null);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot reshape 3 elements into shape [2, 2]")]
+    fn test_tensor_bad_reshape() {
+        eval("reshape([1, 2, 3], [2, 2]);", Object::Null);
+    }
+
+    fn matrix(rows: Vec<Vec<i64>>) -> Object {
+        Object::Array(
+            rows.into_iter()
+                .map(|row| row.into_iter().map(Object::Integer).collect())
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_slices() {
+        let m = "let m = [{1, 2, 3} {4, 5, 6} {7, 8, 9}];";
+        eval(
+            &format!("{} m[1:3, :];", m),
+            matrix(vec![vec![4, 5, 6], vec![7, 8, 9]]),
+        );
+        eval(
+            &format!("{} m[:, 2];", m),
+            matrix(vec![vec![3], vec![6], vec![9]]),
+        );
+        eval(&format!("{} m[::2, 0];", m), matrix(vec![vec![1], vec![7]]));
+        eval(
+            &format!("{} m[1:];", m),
+            matrix(vec![vec![4, 5, 6], vec![7, 8, 9]]),
+        );
+        eval(&format!("{} m[0, 1:10];", m), matrix(vec![vec![2, 3]]));
+        eval(&format!("{} m[2:1, :];", m), matrix(vec![]));
+        eval(
+            &format!("{} m[0, :] = [{{9, 9, 9}}]; m;", m),
+            matrix(vec![vec![9, 9, 9], vec![4, 5, 6], vec![7, 8, 9]]),
+        );
+        eval(
+            &format!(
+                "{} m[:, 1] = 0; m[1:, ::2] = [{{-1, -2}} {{-3, -4}}]; m;",
+                m
+            ),
+            matrix(vec![vec![1, 0, 3], vec![-1, 0, -2], vec![-3, 0, -4]]),
+        );
+
+        let xs = "let xs = [0, 1, 2, 3, 4];";
+        eval(
+            &format!("{} xs[1:3];", xs),
+            list(vec![Object::Integer(1), Object::Integer(2)]),
+        );
+        eval(
+            &format!("{} xs[::2];", xs),
+            list(vec![
+                Object::Integer(0),
+                Object::Integer(2),
+                Object::Integer(4),
+            ]),
+        );
+        eval(
+            &format!("{} xs[3:] = [true, false]; xs;", xs),
+            list(vec![
+                Object::Integer(0),
+                Object::Integer(1),
+                Object::Integer(2),
+                Object::Boolean(true),
+                Object::Boolean(false),
+            ]),
+        );
+
+        let t = "let t = reshape([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11], [2, 3, 2]);";
+        eval(
+            &format!("{} t[:, 1];", t),
+            tensor(vec![2, 2], vec![2, 3, 8, 9]),
+        );
+        eval(
+            &format!("{} t[1, ::2, 1];", t),
+            tensor(vec![2], vec![7, 11]),
+        );
+        eval(
+            &format!(
+                "{} t[0, :, 1] = 0; t[1, 0:1] = reshape([20, 21], [1, 2]); t;",
+                t
+            ),
+            tensor(vec![2, 3, 2], vec![0, 0, 2, 0, 4, 0, 20, 21, 8, 9, 10, 11]),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected an array of shape 1x3")]
+    fn test_slice_shape_mismatch() {
+        eval("let m = [{1, 2, 3}]; m[0, :] = [{1, 2}];", Object::Null);
+    }
+
+    #[test]
+    #[should_panic(expected = "Slice step must be positive, got 0")]
+    fn test_slice_zero_step() {
+        eval("[1, 2, 3][::0];", Object::Null);
+    }
+
+    #[test]
+    fn test_elementwise_operators() {
+        let m = "let m = [{1, 2, 3} {4, 5, 6}];";
+        eval(
+            &format!("{} m + 1;", m),
+            matrix(vec![vec![2, 3, 4], vec![5, 6, 7]]),
+        );
+        eval(
+            &format!("{} 10 - m;", m),
+            matrix(vec![vec![9, 8, 7], vec![6, 5, 4]]),
+        );
+        eval(
+            &format!("{} m * m;", m),
+            matrix(vec![vec![1, 4, 9], vec![16, 25, 36]]),
+        );
+        eval(
+            &format!("{} m / 2;", m),
+            matrix(vec![vec![0, 1, 1], vec![2, 2, 3]]),
+        );
+        eval(
+            &format!("{} m + [{{10, 20, 30}}];", m),
+            matrix(vec![vec![11, 22, 33], vec![14, 25, 36]]),
+        );
+        eval(
+            &format!("{} m * [{{1}} {{-1}}];", m),
+            matrix(vec![vec![1, 2, 3], vec![-4, -5, -6]]),
+        );
+        eval(
+            "[{1} {2}] + [{10, 20}];",
+            matrix(vec![vec![11, 21], vec![12, 22]]),
+        );
+        eval(
+            &format!("{} m -= 1; m;", m),
+            matrix(vec![vec![0, 1, 2], vec![3, 4, 5]]),
+        );
+        eval(
+            &format!("{} reshape(m, [6]) * 2;", m),
+            tensor(vec![6], vec![2, 4, 6, 8, 10, 12]),
+        );
+        eval(
+            "ones([2, 2, 3]) + [{0, 1, 2}];",
+            tensor(vec![2, 2, 3], vec![1, 2, 3, 1, 2, 3, 1, 2, 3, 1, 2, 3]),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot broadcast shapes [2, 3] and [2, 2]")]
+    fn test_elementwise_shape_mismatch() {
+        eval("[{1, 2, 3} {4, 5, 6}] + [{1, 2} {3, 4}];", Object::Null);
+    }
+
+    #[test]
+    #[should_panic(expected = "Integer overflow in -9223372036854775808 / -1")]
+    fn test_integer_division_overflow() {
+        eval(
+            "let min = -9223372036854775807 - 1; min / -1;",
+            Object::Null,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Integer overflow in -9223372036854775808 % -1")]
+    fn test_integer_modulo_overflow() {
+        eval(
+            "let min = -9223372036854775807 - 1; min % -1;",
+            Object::Null,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Division by zero")]
+    fn test_integer_division_by_zero() {
+        eval("let x = 0; 1 / x;", Object::Null);
+    }
+
+    #[test]
+    #[should_panic(expected = "Division by zero")]
+    fn test_elementwise_division_by_zero() {
+        eval("[{1, 2}] / [{1, 0}];", Object::Null);
+    }
+
+    #[test]
+    #[should_panic(expected = "Division by zero")]
+    fn test_constant_division_by_zero() {
+        eval("constant_division([{1, 2}], 0);", Object::Null);
+    }
+
+    #[test]
+    fn test_matmul_operator() {
+        eval(
+            "[{1, 2, 3} {4, 5, 6}] @ [{7, 8} {9, 10} {11, 12}];",
+            matrix(vec![vec![58, 64], vec![139, 154]]),
+        );
+        eval("[{1, 2}] @ [{3} {4}];", matrix(vec![vec![11]]));
+        eval(
+            "[{3} {4}] @ [{1, 2}];",
+            matrix(vec![vec![3, 6], vec![4, 8]]),
+        );
+        eval(
+            "let i = [{1, 0} {0, 1}]; let m = [{1, 2} {3, 4}]; m @ i;",
+            matrix(vec![vec![1, 2], vec![3, 4]]),
+        );
+        // `@` binds tighter than `*` and `+` and associates to the left.
+        eval("2 * [{1, 1}] @ [{1} {2}] + 1;", matrix(vec![vec![7]]));
+        eval(
+            "[{1, 2}] @ [{0, 1} {1, 0}] @ [{2} {3}];",
+            matrix(vec![vec![7]]),
+        );
+        eval(
+            "reshape([1, 2, 3, 4], [2, 2]) @ [{1} {1}];",
+            tensor(vec![2, 1], vec![3, 7]),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot multiply a 2x3 matrix by a 2x3 matrix")]
+    fn test_matmul_dimension_mismatch() {
+        eval(
+            "[{1, 2, 3} {4, 5, 6}] @ [{1, 2, 3} {4, 5, 6}];",
+            Object::Null,
+        );
+    }
+
+    /// Like `eval`, for a matrix result compared with a tolerance.
+    fn eval_approx(input: &str, expected: Vec<Vec<f64>>) {
+        let mut parser = Parser::new(Lexer::new(input));
+        let output = parser.parse();
+        let result = eval_return(output, &mut Env::new());
+        let rows = match result {
+            Object::Array(rows) => rows,
+            other => panic!("Expected a matrix, got {:?}", other),
+        };
+        assert_eq!(rows.len(), expected.len(), "{}", input);
+        for (row, expected) in rows.iter().zip(&expected) {
+            assert_eq!(row.len(), expected.len(), "{}", input);
+            for (el, expected) in row.iter().zip(expected) {
+                let num = match el {
+                    Object::Integer(num) => *num as f64,
+                    Object::Float(num) => *num,
+                    other => panic!("Expected a number, got {:?}", other),
+                };
+                assert!(
+                    (num - expected).abs() < 1e-9,
+                    "{}: {} != {}",
+                    input,
+                    num,
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_floats() {
+        eval("1.5;", Object::Float(1.5));
+        eval("1.5 + 1;", Object::Float(2.5));
+        eval("3 / 2.0;", Object::Float(1.5));
+        eval("-0.25 * 4;", Object::Float(-1.0));
+        eval("0.5 < 1;", Object::Boolean(true));
+        eval("2.0 == 2;", Object::Boolean(true));
+        eval(
+            "[{1, 2}] * 0.5;",
+            Object::Array(vec![vec![Object::Float(0.5), Object::Float(1.0)]]),
+        );
+        assert_eq!(Object::Float(2.0).to_string(), "2.0");
+        assert_eq!(Object::Float(1e20).to_string(), "100000000000000000000.0");
+        assert_eq!(Object::Float(-0.25).to_string(), "-0.25");
+    }
+
+    #[test]
+    fn test_linear_algebra() {
+        eval("det([{1, 2} {3, 4}]);", Object::Integer(-2));
+        eval(
+            "det([{6, 1, 1} {4, -2, 5} {2, 8, 7}]);",
+            Object::Integer(-306),
+        );
+        eval("det([{0, 1} {1, 0}]);", Object::Integer(-1));
+        eval("det([{1, 2, 3} {4, 5, 6} {7, 8, 9}]);", Object::Integer(0));
+        eval("det([{0.5, 1} {1, 4}]);", Object::Float(1.0));
+        eval("rank([{1, 2} {2, 4}]);", Object::Integer(1));
+        eval(
+            "rank([{1, 2, 3} {4, 5, 6} {7, 8, 10}]);",
+            Object::Integer(3),
+        );
+        eval("rank([{0, 0, 1} {0, 0, 2}]);", Object::Integer(1));
+        eval("trace([{1, 2} {3, 4}]);", Object::Integer(5));
+        eval(
+            "inverse([{2, 0} {0, 4}]);",
+            Object::Array(vec![
+                vec![Object::Float(0.5), Object::Float(0.0)],
+                vec![Object::Float(0.0), Object::Float(0.25)],
+            ]),
+        );
+        eval_approx(
+            "inverse([{4, 7} {2, 6}]);",
+            vec![vec![0.6, -0.7], vec![-0.2, 0.4]],
+        );
+        eval_approx(
+            "let a = [{1, 2, 0} {0, 1, 3} {4, 0, 1}]; a @ inverse(a);",
+            vec![
+                vec![1.0, 0.0, 0.0],
+                vec![0.0, 1.0, 0.0],
+                vec![0.0, 0.0, 1.0],
+            ],
+        );
+        eval(
+            "solve([{2, 0} {0, 4}], [2, 2]);",
+            list(vec![Object::Float(1.0), Object::Float(0.5)]),
+        );
+        eval_approx(
+            "solve([{2, 1} {1, 3}], [{3} {5}]);",
+            vec![vec![0.8], vec![1.4]],
+        );
+        eval_approx(
+            "let a = [{0, 2, 1} {1, 1, 1} {2, 1, 0}]; let d = lu(a); d[\"P\"] @ a - d[\"L\"] @ d[\"U\"];",
+            vec![vec![0.0; 3]; 3],
+        );
+        eval_approx(
+            "let d = lu([{0, 2} {1, 1}]); d[\"L\"];",
+            vec![vec![1.0, 0.0], vec![0.0, 1.0]],
+        );
+        eval_approx(
+            "let a = [{1, 2} {3, 4} {5, 6}]; let d = qr(a); d[\"Q\"] @ d[\"R\"];",
+            vec![vec![1.0, 2.0], vec![3.0, 4.0], vec![5.0, 6.0]],
+        );
+        eval_approx(
+            "let q = qr([{1, 2} {3, 4} {5, 6}])[\"Q\"]; transpose_matrix(q) @ q;",
+            vec![vec![1.0, 0.0], vec![0.0, 1.0]],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Matrix is singular")]
+    fn test_inverse_singular() {
+        eval("inverse([{1, 2} {2, 4}]);", Object::Null);
+    }
+
+    #[test]
+    #[should_panic(expected = "det requires a square matrix, got 2x3")]
+    fn test_det_not_square() {
+        eval("det([{1, 2, 3} {4, 5, 6}]);", Object::Null);
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected a matrix of finite numbers, got NaN")]
+    fn test_det_nan() {
+        eval("det([{0.0 / 0.0, 1.0} {1.0, 2.0}]);", Object::Null);
+    }
+
+    #[test]
+    fn test_matrix_constructors() {
+        eval("zeros(2, 3);", matrix(vec![vec![0, 0, 0], vec![0, 0, 0]]));
+        eval("ones(1, 2);", matrix(vec![vec![1, 1]]));
+        eval("zeros(0, 3);", matrix(vec![]));
+        eval("zeros([2, 3]);", matrix(vec![vec![0, 0, 0], vec![0, 0, 0]]));
+        eval("ones([1, 2]);", matrix(vec![vec![1, 1]]));
+        eval("det(ones([2, 2]));", Object::Integer(0));
+        eval(
+            "identity(3);",
+            matrix(vec![vec![1, 0, 0], vec![0, 1, 0], vec![0, 0, 1]]),
+        );
+        eval("diag([1, 2]);", matrix(vec![vec![1, 0], vec![0, 2]]));
+        eval(
+            "diag([{1, 2, 3} {4, 5, 6}]);",
+            list(vec![Object::Integer(1), Object::Integer(5)]),
+        );
+        eval(
+            "arange(0, 10, 3);",
+            list(vec![
+                Object::Integer(0),
+                Object::Integer(3),
+                Object::Integer(6),
+                Object::Integer(9),
+            ]),
+        );
+        eval(
+            "arange(3, 0, -1);",
+            list(vec![
+                Object::Integer(3),
+                Object::Integer(2),
+                Object::Integer(1),
+            ]),
+        );
+        eval("arange(2, 2);", list(vec![]));
+        eval(
+            "arange(0, 1, 0.25);",
+            list(vec![
+                Object::Float(0.0),
+                Object::Float(0.25),
+                Object::Float(0.5),
+                Object::Float(0.75),
+            ]),
+        );
+        eval(
+            "linspace(0, 1, 5);",
+            list(vec![
+                Object::Float(0.0),
+                Object::Float(0.25),
+                Object::Float(0.5),
+                Object::Float(0.75),
+                Object::Float(1.0),
+            ]),
+        );
+        eval("linspace(2, 3, 1);", list(vec![Object::Float(2.0)]));
+        eval(
+            "reshape(arange(0, 4), [2, 2]);",
+            tensor(vec![2, 2], vec![0, 1, 2, 3]),
+        );
+    }
+
+    #[test]
+    fn test_random_matrix() {
+        let run = |input: &str| {
+            let mut parser = Parser::new(Lexer::new(input));
+            eval_return(parser.parse(), &mut Env::new())
+        };
+        let first = run("random_matrix(4, 5, -3, 3, 42);");
+        assert_eq!(first, run("random_matrix(4, 5, -3, 3, 42);"));
+        assert_ne!(first, run("random_matrix(4, 5, -3, 3, 43);"));
+        match first {
+            Object::Array(rows) => {
+                assert_eq!(rows.len(), 4);
+                for row in rows {
+                    assert_eq!(row.len(), 5);
+                    for el in row {
+                        match el {
+                            Object::Integer(num) => assert!(num >= -3 && num <= 3),
+                            other => panic!("Expected an integer, got {:?}", other),
+                        }
+                    }
+                }
+            }
+            other => panic!("Expected a matrix, got {:?}", other),
+        }
+        match run("random_matrix(3, 3, 0.0, 0.5, 7);") {
+            Object::Array(rows) => {
+                for el in rows.into_iter().flatten() {
+                    match el {
+                        Object::Float(num) => assert!(num >= 0.0 && num < 0.5),
+                        other => panic!("Expected a float, got {:?}", other),
+                    }
+                }
+            }
+            other => panic!("Expected a matrix, got {:?}", other),
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "random_matrix needs lo <= hi, got 1 and 0.5")]
+    fn test_random_matrix_float_bounds() {
+        eval("random_matrix(2, 2, 1, 0.5, 7);", Object::Null);
+    }
+
+    #[test]
+    #[should_panic(expected = "median of values containing NaN")]
+    fn test_median_nan() {
+        eval("median([{1.0, 0.0 / 0.0, 2.0}]);", Object::Null);
+    }
+
+    #[test]
+    fn test_statistics() {
+        let m = "let m = [{1, 5, 3} {4, 2, 9}];";
+        eval(&format!("{} mean(m);", m), Object::Float(4.0));
+        eval(
+            &format!("{} mean(m, 0);", m),
+            Object::Array(vec![vec![
+                Object::Float(2.5),
+                Object::Float(3.5),
+                Object::Float(6.0),
+            ]]),
+        );
+        eval(
+            &format!("{} mean(m, 1);", m),
+            Object::Array(vec![vec![Object::Float(3.0)], vec![Object::Float(5.0)]]),
+        );
+        eval(&format!("{} median(m);", m), Object::Float(3.5));
+        eval(
+            &format!("{} median(m, 1);", m),
+            Object::Array(vec![vec![Object::Float(3.0)], vec![Object::Float(4.0)]]),
+        );
+        eval("variance([2, 4, 4, 4, 5, 5, 7, 9]);", Object::Float(4.0));
+        eval("stddev([2, 4, 4, 4, 5, 5, 7, 9]);", Object::Float(2.0));
+        eval(
+            &format!("{} variance(m, 0);", m),
+            Object::Array(vec![vec![
+                Object::Float(2.25),
+                Object::Float(2.25),
+                Object::Float(9.0),
+            ]]),
+        );
+        eval(&format!("{} argmax(m);", m), Object::Integer(5));
+        eval(
+            &format!("{} let i = argmax(m); m[i / 3, i % 3];", m),
+            Object::Integer(9),
+        );
+        eval(&format!("{} argmax(m, 0);", m), matrix(vec![vec![1, 0, 1]]));
+        eval(
+            &format!("{} argmin(m, 1);", m),
+            matrix(vec![vec![0], vec![1]]),
+        );
+        eval(
+            &format!("{} cumsum(m);", m),
+            list(
+                vec![1, 6, 9, 13, 15, 24]
+                    .into_iter()
+                    .map(Object::Integer)
+                    .collect(),
+            ),
+        );
+        eval(
+            &format!("{} cumsum(m, 0);", m),
+            matrix(vec![vec![1, 5, 3], vec![5, 7, 12]]),
+        );
+        eval(
+            &format!("{} cumsum(m, 1);", m),
+            matrix(vec![vec![1, 6, 9], vec![4, 6, 15]]),
+        );
+        eval(&format!("{} prod(m);", m), Object::Integer(1080));
+        eval(
+            &format!("{} prod(m, 1);", m),
+            matrix(vec![vec![15], vec![72]]),
+        );
+        eval(&format!("{} sum(m, 0);", m), matrix(vec![vec![5, 7, 12]]));
+        eval(&format!("{} max(m, 1);", m), matrix(vec![vec![5], vec![9]]));
+        eval(&format!("{} min(m, 0);", m), matrix(vec![vec![1, 2, 3]]));
+        eval("sum([1, 2.5]);", Object::Float(3.5));
+        eval("prod([0.5, 4]);", Object::Float(2.0));
+        eval(
+            &format!("{} m - mean(m, 1);", m),
+            Object::Array(vec![
+                vec![Object::Float(-2.0), Object::Float(2.0), Object::Float(0.0)],
+                vec![Object::Float(-1.0), Object::Float(-3.0), Object::Float(4.0)],
+            ]),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "axis must be 0 or 1, got 2")]
+    fn test_statistics_bad_axis() {
+        eval("mean([{1, 2}], 2);", Object::Null);
+    }
+
+    #[test]
+    fn test_matrix_builtins_keep_every_element() {
+        eval("sum([{1, 2.5}]);", Object::Float(3.5));
+        eval("max([{1, 2.5}]);", Object::Float(2.5));
+        eval(
+            "get_row(1, [{1, true}]);",
+            Object::Array(vec![vec![Object::Integer(1), Object::Boolean(true)]]),
+        );
+        eval(
+            "get_col(2, [{1, 2.5}]);",
+            Object::Array(vec![vec![Object::Float(2.5)]]),
+        );
+    }
+
+    #[test]
+    fn test_get_row_and_col_match_slices() {
+        let m = "let m = [{1, 2, 3} {4, 5, 6}];";
+        eval(
+            &format!("{} get_row(2, m);", m),
+            matrix(vec![vec![4, 5, 6]]),
+        );
+        eval(&format!("{} m[1, :];", m), matrix(vec![vec![4, 5, 6]]));
+        eval(&format!("{} get_col(3, m);", m), matrix(vec![vec![3, 6]]));
+        eval(&format!("{} m[:, 2];", m), matrix(vec![vec![3], vec![6]]));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "add_matrix expects a matrix of integers, expected an integer, got Float(2.5)"
+    )]
+    fn test_integer_builtin_rejects_floats() {
+        eval("add_matrix([{1, 2.5}], [{1, 2}]);", Object::Null);
+    }
+
+    fn ints(nums: Vec<i64>) -> Object {
+        list(nums.into_iter().map(Object::Integer).collect())
+    }
+
+    #[test]
+    fn test_named_functions() {
+        eval("fn double(x) { x * 2 } double(4);", Object::Integer(8));
+        eval(
+            "fn fact(n) { if (n < 2) { 1 } else { n * fact(n - 1) } } fact(5);",
+            Object::Integer(120),
+        );
+        eval(
+            "fn apply(f, x) { f(x) } fn inc(x) { x + 1 } apply(inc, 1);",
+            Object::Integer(2),
+        );
+    }
+
+    #[test]
+    fn test_call_any_callee() {
+        eval(
+            "fn double(x) { x * 2 } let fs = [double]; fs[0](3);",
+            Object::Integer(6),
+        );
+        eval(
+            "fn make() { fn inc(x) { x + 1 } } make()(1);",
+            Object::Integer(2),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "1 is not callable")]
+    fn test_call_not_callable() {
+        eval("let xs = [1]; xs[0](2);", Object::Null);
+    }
+
+    #[test]
+    fn test_functions_see_top_level_functions() {
+        eval(
+            "fn sq(x) { x * x } fn f(x) { sq(x) + 1 } f(3);",
+            Object::Integer(10),
+        );
+        eval(
+            "fn even(n) { if (n == 0) { true } else { odd(n - 1) } } \
+             fn odd(n) { if (n == 0) { false } else { even(n - 1) } } even(4);",
+            Object::Boolean(true),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Unrecognized function \"g\"")]
+    fn test_block_functions_stay_local() {
+        eval("{ fn g() { 1 } } fn f() { g() } f();", Object::Null);
+    }
+
+    #[test]
+    #[should_panic(expected = "y not found")]
+    fn test_functions_do_not_capture() {
+        eval("let y = 1; fn f() { y } f();", Object::Null);
+    }
+
+    #[test]
+    fn test_map_iteration() {
+        let m = "let m = {\"b\": 2, \"a\": 1, \"c\": 3};";
+        eval(
+            &format!("{} fn key(entry) {{ entry[0] }} map(key, m);", m),
+            list(vec![
+                Object::String("a".to_string()),
+                Object::String("b".to_string()),
+                Object::String("c".to_string()),
+            ]),
+        );
+        eval(
+            &format!(
+                "{} fn odd(entry) {{ entry[1] % 2 == 1 }} filter(odd, m);",
+                m
+            ),
+            map(vec![
+                (MapKey::String("a".to_string()), Object::Integer(1)),
+                (MapKey::String("c".to_string()), Object::Integer(3)),
+            ]),
+        );
+        eval(
+            &format!(
+                "{} fn add(acc, entry) {{ acc * 10 + entry[1] }} reduce(add, 0, m);",
+                m
+            ),
+            Object::Integer(123),
+        );
+        eval(
+            &format!("{} reverse(m)[0];", m),
+            list(vec![Object::String("c".to_string()), Object::Integer(3)]),
+        );
+    }
+
+    #[test]
+    fn test_collection_builtins() {
+        let m = "let m = [{3, 1} {1, 2} {2, 0}];";
+        eval(
+            "fn double(x) { x * 2 } map(double, [1, 2, 3]);",
+            ints(vec![2, 4, 6]),
+        );
+        eval(
+            &format!("{} fn total(row) {{ sum(row) }} map(total, m);", m),
+            ints(vec![4, 3, 2]),
+        );
+        eval(
+            "fn even(x) { x % 2 == 0 } filter(even, [1, 2, 3, 4]);",
+            ints(vec![2, 4]),
+        );
+        eval(
+            &format!(
+                "{} fn first_big(row) {{ row[0] > 1 }} filter(first_big, m);",
+                m
+            ),
+            matrix(vec![vec![3, 1], vec![2, 0]]),
+        );
+        eval(
+            "fn add(acc, x) { acc + x } reduce(add, 10, [1, 2, 3]);",
+            Object::Integer(16),
+        );
+        eval(
+            &format!(
+                "{} fn add(acc, row) {{ acc + row[1] }} reduce(add, 0, m);",
+                m
+            ),
+            Object::Integer(3),
+        );
+        eval(
+            "zip([1, 2, 3], [\"a\", \"b\"]);",
+            list(vec![
+                list(vec![Object::Integer(1), Object::String("a".to_string())]),
+                list(vec![Object::Integer(2), Object::String("b".to_string())]),
+            ]),
+        );
+        eval(
+            "enumerate([5, 6]);",
+            list(vec![ints(vec![0, 5]), ints(vec![1, 6])]),
+        );
+        eval(
+            "sort([3, 1.5, 2]);",
+            list(vec![
+                Object::Float(1.5),
+                Object::Integer(2),
+                Object::Integer(3),
+            ]),
+        );
+        eval(
+            "sort([\"b\", \"a\"]);",
+            list(vec![
+                Object::String("a".to_string()),
+                Object::String("b".to_string()),
+            ]),
+        );
+        eval(
+            &format!("{} sort(m);", m),
+            matrix(vec![vec![1, 2], vec![2, 0], vec![3, 1]]),
+        );
+        eval(
+            "fn neg(x) { -x } sort_by(neg, [2, 3, 1]);",
+            ints(vec![3, 2, 1]),
+        );
+        eval(
+            &format!("{} fn second(row) {{ row[1] }} sort_by(second, m);", m),
+            matrix(vec![vec![2, 0], vec![3, 1], vec![1, 2]]),
+        );
+        eval("reverse([1, 2, 3]);", ints(vec![3, 2, 1]));
+        eval(
+            &format!("{} reverse(m);", m),
+            matrix(vec![vec![2, 0], vec![1, 2], vec![3, 1]]),
+        );
+    }
+
+    fn eval_in(input: &str, env: &mut Env) -> Object {
+        let mut parser = Parser::new(Lexer::new(input));
+        eval_return(parser.parse(), env)
+    }
+
+    fn native_env() -> Env {
+        let mut env = Env::new();
+        env.register(NativeFunction::new(
+            "clamp",
+            vec![ParamType::Number, ParamType::Number, ParamType::Number],
+            "clamp(x, lo, hi): x limited to the range [lo, hi].",
+            |args| match (&args[0], &args[1], &args[2]) {
+                (Object::Integer(x), Object::Integer(lo), Object::Integer(hi)) => {
+                    Object::Integer(*x.max(lo).min(hi))
+                }
+                _ => Object::Float(
+                    number_value(&args[0])
+                        .max(number_value(&args[1]))
+                        .min(number_value(&args[2])),
+                ),
+            },
+        ));
+        env.register(NativeFunction::new(
+            "size",
+            vec![ParamType::List],
+            "size(xs): overrides the builtin.",
+            |_| Object::Integer(-1),
+        ));
+        env
+    }
+
+    #[test]
+    fn test_native_functions() {
+        let mut env = native_env();
+        assert_eq!(eval_in("clamp(15, 0, 10);", &mut env), Object::Integer(10));
+        assert_eq!(eval_in("clamp(0.5, 1, 2);", &mut env), Object::Float(1.0));
+        assert_eq!(
+            eval_in("fn f(x) { clamp(x, 0, 1) } f(-3);", &mut env),
+            Object::Integer(0)
+        );
+        assert_eq!(eval_in("size([1, 2]);", &mut env), Object::Integer(-1));
+        assert_eq!(
+            eval_in("help(\"clamp\");", &mut env),
+            Object::String("clamp(x, lo, hi): x limited to the range [lo, hi].".to_string())
+        );
+        let mut names: Vec<&str> = env.natives().map(|native| native.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, vec!["clamp", "size"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Argument 2 of clamp must be a number, got Boolean(true)")]
+    fn test_native_function_types() {
+        eval_in("clamp(1, true, 2);", &mut native_env());
+    }
+
+    #[test]
+    #[should_panic(expected = "clamp expects 3 arguments, got 1")]
+    fn test_native_function_arity() {
+        eval_in("clamp(1);", &mut native_env());
+    }
+
+    #[test]
+    fn test_bools() {
+        eval("false;", Object::Boolean(false));
+        eval("true;", Object::Boolean(true));
+        eval("!false;", Object::Boolean(true));
+        eval("!true;", Object::Boolean(false));
+    }
+    #[test]
+    fn test_arrays() {
+        let one = Object::Integer(1);
+        let two = Object::Integer(2);
+        let three = Object::Integer(3);
+        let four = Object::Integer(4);
+        let five = Object::Integer(5);
+        eval(
+            "[{1, 2, 3, 4, 5}];",
+            Object::Array([[one, two, three, four, five].to_vec()].to_vec()),
+        );
+
+        let one = Object::Integer(1);
+        let two = Object::Integer(2);
+        let three = Object::Integer(3);
+        let four = Object::Integer(4);
+        let five = Object::Integer(5);
+        eval(
+            "[{1+1-1, 2+2-2, 3+1-1, 4+0, 5/1} {1, 2, 3, 4, 5}];",
+            Object::Array(
+                [
+                    [one, two, three, four, five].to_vec(),
+                    [
+                        Object::Integer(1),
+                        Object::Integer(2),
+                        Object::Integer(3),
+                        Object::Integer(4),
+                        Object::Integer(5),
+                    ]
+                    .to_vec(),
+                ]
+                .to_vec(),
+            ),
+        );
+    }
+
+    #[test]
+    fn test_built_in() {
+        eval("size([{1, 2, 3, 4, 5}]);", Object::Integer(5));
+        eval(
+            "size([{1, 2, 3, 4, 5} {10, 12, 123, 155, 166} {100, 99, 98, 97, 96}]);",
+            Object::Integer(15),
+        );
+        eval(
+            "max([{1, 2, 3, 4, 5} {10, 12, 123, 155, 166} {100, 99, 98, 97, 96}]);",
+            Object::Integer(166),
+        );
+        eval(
+            "min([{1, 2, 3, 4, 5} {10, 12, 123, 155, 166} {100, 99, 98, 97, 96}]);",
+            Object::Integer(1),
+        );
+        eval(
+            "sum([{1, 2, 3, 4, 5} {3, 4, 1, 2, 0} {10, 10, 10, 10, 10} ]);",
+            Object::Integer(75),
+        );
+        eval(
+            "constant_product([{1, 2}], 10);",
+            Object::Array([[Object::Integer(10), Object::Integer(20)].to_vec()].to_vec()),
+        );
+        eval(
+            "dot_product([{2, 4}], [{1, 2} {3, 4}]);",
+            Object::Array([[Object::Integer(14), Object::Integer(20)].to_vec()].to_vec()),
         );
-        // eval(
-        //     "dot_product([{2, 4}], [{1, 2} {3, 4}]);",
-        //     Object::Array([[Object::Integer(58), Object::Integer(64)].to_vec()].to_vec()),
-        // );
         eval(
             "transpose_matrix([{1, 2, 3} {4, 5, 6}]);",
             Object::Array(
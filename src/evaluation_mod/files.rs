@@ -0,0 +1,140 @@
+use crate::evaluation_mod::env::Env;
+use crate::evaluation_mod::evaluate::Object;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+
+fn io_error(path: &str, err: std::io::Error) -> ! {
+    panic!("{}: {}", path, err)
+}
+
+/// Opens a path that passed `check_access` without following a symbolic
+/// link, so one swapped in since the check cannot redirect the access out of
+/// the allowed directories.
+fn open_checked(resolved: &Path, options: &mut OpenOptions) -> io::Result<File> {
+    #[cfg(unix)]
+    options.custom_flags(libc::O_NOFOLLOW);
+    options.open(resolved)
+}
+
+pub fn read_file(path: &str, env: &Env) -> Object {
+    let resolved = env.check_access(path);
+    let mut contents = String::new();
+    let read = open_checked(&resolved, OpenOptions::new().read(true))
+        .and_then(|mut file| file.read_to_string(&mut contents));
+    match read {
+        Ok(_) => Object::String(contents),
+        Err(err) => io_error(path, err),
+    }
+}
+
+pub fn write_file(path: &str, contents: &str, env: &Env) -> Object {
+    let resolved = env.check_access(path);
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    let written = open_checked(&resolved, &mut options)
+        .and_then(|mut file| file.write_all(contents.as_bytes()));
+    if let Err(err) = written {
+        io_error(path, err);
+    }
+    Object::Null
+}
+
+pub fn read_lines(path: &str, env: &Env) -> Object {
+    match read_file(path, env) {
+        Object::String(contents) => Object::List(
+            contents
+                .lines()
+                .map(|line| Object::String(line.to_string()))
+                .collect(),
+        ),
+        _ => unreachable!(),
+    }
+}
+
+/// Splits one CSV record into fields. Fields may be double-quoted, in which
+/// case they can hold commas and `""` stands for a quote.
+fn split_record(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match (ch, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            ('"', _) => quoted = !quoted,
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            (ch, _) => field.push(ch),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// Integers and floats become numbers; any other field stays a string.
+fn parse_field(field: &str) -> Object {
+    let field = field.trim();
+    if let Ok(num) = field.parse::<i64>() {
+        Object::Integer(num)
+    } else if let Ok(num) = field.parse::<f64>() {
+        Object::Float(num)
+    } else {
+        Object::String(field.to_string())
+    }
+}
+
+/// Reads a CSV file as a matrix, one row per non-empty line.
+pub fn read_csv(path: &str, env: &Env) -> Object {
+    let contents = match read_file(path, env) {
+        Object::String(contents) => contents,
+        _ => unreachable!(),
+    };
+    let rows: Vec<Vec<Object>> = contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            split_record(line)
+                .iter()
+                .map(|field| parse_field(field))
+                .collect()
+        })
+        .collect();
+    if let Some(first) = rows.first() {
+        for (i, row) in rows.iter().enumerate() {
+            if row.len() != first.len() {
+                panic!(
+                    "{}: row {} has {} fields, expected {}",
+                    path,
+                    i + 1,
+                    row.len(),
+                    first.len()
+                );
+            }
+        }
+    }
+    Object::Array(rows)
+}
+
+fn csv_field(el: &Object) -> String {
+    let text = el.to_string();
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
+
+pub fn write_csv(path: &str, els: &[Vec<Object>], env: &Env) -> Object {
+    let mut contents = String::new();
+    for row in els {
+        let fields: Vec<String> = row.iter().map(csv_field).collect();
+        contents.push_str(&fields.join(","));
+        contents.push('\n');
+    }
+    write_file(path, &contents, env)
+}
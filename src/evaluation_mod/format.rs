@@ -0,0 +1,78 @@
+use crate::evaluation_mod::evaluate::Object;
+
+/// How `print` and `format` show a value: matrices as an aligned table and
+/// everything else as its `Display` form.
+pub fn to_text(object: &Object) -> String {
+    match object {
+        Object::Array(els) => matrix_table(els),
+        other => other.to_string(),
+    }
+}
+
+/// One line per row with right-aligned columns, e.g.
+///
+/// ```text
+/// [ 1  -2]
+/// [30   4]
+/// ```
+pub fn matrix_table(els: &[Vec<Object>]) -> String {
+    if els.is_empty() {
+        return "[]".to_string();
+    }
+    let cells: Vec<Vec<String>> = els
+        .iter()
+        .map(|row| row.iter().map(Object::repr).collect())
+        .collect();
+    let cols = cells.iter().map(|row| row.len()).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..cols)
+        .map(|col| {
+            cells
+                .iter()
+                .filter_map(|row| row.get(col))
+                .map(|cell| cell.chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    cells
+        .iter()
+        .map(|row| {
+            let padded: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, &width)| format!("{:>width$}", cell, width = width))
+                .collect();
+            format!("[{}]", padded.join("  "))
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Replaces each `{}` in `template` with the next argument; `{{` and `}}`
+/// stand for literal braces.
+pub fn format(template: &str, args: &[Object]) -> String {
+    let mut result = String::new();
+    let mut args_iter = args.iter();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                result.push(c);
+            }
+            ('{', Some('}')) => {
+                chars.next();
+                match args_iter.next() {
+                    Some(arg) => result.push_str(&to_text(arg)),
+                    None => panic!("Too few arguments for format string {:?}", template),
+                }
+            }
+            ('{', _) | ('}', _) => panic!("Unmatched brace in format string {:?}", template),
+            _ => result.push(c),
+        }
+    }
+    if args_iter.next().is_some() {
+        panic!("Too many arguments for format string {:?}", template);
+    }
+    result
+}
@@ -0,0 +1,61 @@
+use crate::evaluation_mod::env::Env;
+use crate::evaluation_mod::evaluate::Object;
+use std::io::{self, Write};
+
+fn next_line(name: &str, env: &Env) -> String {
+    match env.read_line() {
+        Some(line) => line,
+        None => panic!("{}: end of input", name),
+    }
+}
+
+/// `input()` and `input(prompt)`: the next line of input, without its line
+/// ending.
+pub fn input(prompt: Option<&str>, env: &Env) -> Object {
+    if let Some(prompt) = prompt {
+        let mut stdout = io::stdout();
+        write!(stdout, "{}", prompt).unwrap();
+        stdout.flush().unwrap();
+    }
+    Object::String(next_line("input", env))
+}
+
+pub fn read_int(env: &Env) -> Object {
+    let line = next_line("read_int", env);
+    match line.trim().parse() {
+        Ok(num) => Object::Integer(num),
+        Err(_) => panic!("read_int expects an integer, got {:?}", line),
+    }
+}
+
+/// Reads `rows * cols` numbers separated by whitespace or commas, row by row.
+/// They may be spread over any number of lines.
+pub fn read_matrix(rows: usize, cols: usize, env: &Env) -> Object {
+    let mut nums = vec![];
+    while nums.len() < rows * cols {
+        let line = next_line("read_matrix", env);
+        for field in line
+            .split(|ch: char| ch == ',' || ch.is_whitespace())
+            .filter(|field| !field.is_empty())
+        {
+            nums.push(if let Ok(num) = field.parse::<i64>() {
+                Object::Integer(num)
+            } else if let Ok(num) = field.parse::<f64>() {
+                Object::Float(num)
+            } else {
+                panic!("read_matrix expects numbers, got {:?}", field)
+            });
+        }
+    }
+    if nums.len() > rows * cols {
+        panic!(
+            "read_matrix expects {} numbers, got {}",
+            rows * cols,
+            nums.len()
+        );
+    }
+    if cols == 0 {
+        return Object::Array(vec![vec![]; rows]);
+    }
+    Object::Array(nums.chunks(cols).map(|row| row.to_vec()).collect())
+}
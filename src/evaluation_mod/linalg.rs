@@ -0,0 +1,357 @@
+use crate::evaluation_mod::evaluate::{MapKey, Object};
+use crate::evaluation_mod::tensor;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+/// Pivots smaller than this are treated as zero in floating point elimination.
+const EPSILON: f64 = 1e-10;
+
+/// The matrix as integers, or `None` if it holds any float.
+fn integer_matrix(els: &[Vec<Object>]) -> Option<Vec<Vec<i128>>> {
+    let mut rows = vec![];
+    for row in els {
+        let mut ints = vec![];
+        for el in row {
+            match el {
+                Object::Integer(num) => ints.push(*num as i128),
+                Object::Float(_) => return None,
+                other => panic!("Expected a matrix of numbers, got {:?}", other),
+            }
+        }
+        rows.push(ints);
+    }
+    Some(rows)
+}
+
+/// The entries as floats. NaN and infinite entries are rejected, since no
+/// pivot can be chosen among them.
+fn float_matrix(els: &[Vec<Object>]) -> Vec<Vec<f64>> {
+    els.iter()
+        .map(|row| {
+            row.iter()
+                .map(|el| match el {
+                    Object::Integer(num) => *num as f64,
+                    Object::Float(num) if num.is_finite() => *num,
+                    Object::Float(num) => {
+                        panic!("Expected a matrix of finite numbers, got {}", num)
+                    }
+                    other => panic!("Expected a matrix of numbers, got {:?}", other),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn from_floats(rows: Vec<Vec<f64>>) -> Object {
+    Object::Array(
+        rows.into_iter()
+            .map(|row| row.into_iter().map(Object::Float).collect())
+            .collect(),
+    )
+}
+
+fn to_integer(num: i128) -> Object {
+    match i64::try_from(num) {
+        Ok(num) => Object::Integer(num),
+        Err(_) => panic!("Integer overflow in matrix computation"),
+    }
+}
+
+/// The number of rows and columns of a rectangular matrix.
+fn dimensions(els: &[Vec<Object>]) -> (usize, usize) {
+    let (shape, _) = tensor::from_matrix(els);
+    (shape[0], shape[1])
+}
+
+fn square_size(name: &str, els: &[Vec<Object>]) -> usize {
+    let (rows, cols) = dimensions(els);
+    if rows != cols {
+        panic!("{} requires a square matrix, got {}x{}", name, rows, cols);
+    }
+    rows
+}
+
+/// Fraction-free (Bareiss) elimination: every division is exact, so integer
+/// matrices never leave the integers. Returns the rank and the sign of the
+/// row permutation; for a full rank square matrix the last pivot is the
+/// determinant up to that sign.
+fn bareiss(m: &mut Vec<Vec<i128>>) -> (usize, i128) {
+    let rows = m.len();
+    let cols = m.first().map_or(0, |row| row.len());
+    let (mut rank, mut sign, mut prev) = (0, 1, 1);
+    for col in 0..cols {
+        if rank == rows {
+            break;
+        }
+        let pivot = match (rank..rows).find(|&row| m[row][col] != 0) {
+            Some(pivot) => pivot,
+            None => continue,
+        };
+        if pivot != rank {
+            m.swap(pivot, rank);
+            sign = -sign;
+        }
+        for row in rank + 1..rows {
+            for j in col + 1..cols {
+                let cross = m[row][j]
+                    .checked_mul(m[rank][col])
+                    .and_then(|a| m[row][col].checked_mul(m[rank][j]).map(|b| a - b));
+                match cross {
+                    Some(cross) => m[row][j] = cross / prev,
+                    None => panic!("Integer overflow in matrix computation"),
+                }
+            }
+            m[row][col] = 0;
+        }
+        prev = m[rank][col];
+        rank += 1;
+    }
+    (rank, sign)
+}
+
+/// Gaussian elimination with partial pivoting, in place. Returns the rank and
+/// the sign of the row permutation.
+fn eliminate(m: &mut Vec<Vec<f64>>) -> (usize, f64) {
+    let rows = m.len();
+    let cols = m.first().map_or(0, |row| row.len());
+    let (mut rank, mut sign) = (0, 1.0);
+    for col in 0..cols {
+        if rank == rows {
+            break;
+        }
+        let pivot = (rank..rows)
+            .max_by(|&a, &b| m[a][col].abs().total_cmp(&m[b][col].abs()))
+            .unwrap();
+        if m[pivot][col].abs() < EPSILON {
+            continue;
+        }
+        if pivot != rank {
+            m.swap(pivot, rank);
+            sign = -sign;
+        }
+        for row in rank + 1..rows {
+            let factor = m[row][col] / m[rank][col];
+            for j in col..cols {
+                m[row][j] -= factor * m[rank][j];
+            }
+        }
+        rank += 1;
+    }
+    (rank, sign)
+}
+
+/// The determinant, exact for integer matrices.
+pub fn det(els: &[Vec<Object>]) -> Object {
+    let n = square_size("det", els);
+    if n == 0 {
+        return Object::Integer(1);
+    }
+    match integer_matrix(els) {
+        Some(mut m) => match bareiss(&mut m) {
+            (rank, sign) if rank == n => to_integer(sign * m[n - 1][n - 1]),
+            _ => Object::Integer(0),
+        },
+        None => {
+            let mut m = float_matrix(els);
+            match eliminate(&mut m) {
+                (rank, sign) if rank == n => {
+                    Object::Float((0..n).fold(sign, |det, i| det * m[i][i]))
+                }
+                _ => Object::Float(0.0),
+            }
+        }
+    }
+}
+
+/// The rank, exact for integer matrices.
+pub fn rank(els: &[Vec<Object>]) -> Object {
+    dimensions(els);
+    let rank = match integer_matrix(els) {
+        Some(mut m) => bareiss(&mut m).0,
+        None => eliminate(&mut float_matrix(els)).0,
+    };
+    Object::Integer(rank as i64)
+}
+
+pub fn trace(els: &[Vec<Object>]) -> Object {
+    let n = square_size("trace", els);
+    match integer_matrix(els) {
+        Some(m) => to_integer((0..n).map(|i| m[i][i]).sum()),
+        None => {
+            let m = float_matrix(els);
+            Object::Float((0..n).map(|i| m[i][i]).sum())
+        }
+    }
+}
+
+/// Panics unless the square matrix is invertible. Integer matrices are
+/// checked exactly rather than against `EPSILON`.
+fn check_invertible(els: &[Vec<Object>]) {
+    let singular = match integer_matrix(els) {
+        Some(mut m) => bareiss(&mut m).0 < els.len(),
+        None => eliminate(&mut float_matrix(els)).0 < els.len(),
+    };
+    if singular {
+        panic!("Matrix is singular");
+    }
+}
+
+/// Solves `a x = b` for every column of `b` by Gauss-Jordan elimination with
+/// partial pivoting. `a` must be invertible.
+fn gauss_jordan(mut a: Vec<Vec<f64>>, mut b: Vec<Vec<f64>>) -> Vec<Vec<f64>> {
+    let n = a.len();
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|&x, &y| a[x][col].abs().total_cmp(&a[y][col].abs()))
+            .unwrap();
+        if a[pivot][col].abs() < EPSILON {
+            panic!("Matrix is singular");
+        }
+        a.swap(pivot, col);
+        b.swap(pivot, col);
+        let scale = a[col][col];
+        a[col].iter_mut().for_each(|el| *el /= scale);
+        b[col].iter_mut().for_each(|el| *el /= scale);
+        for row in 0..n {
+            if row == col || a[row][col] == 0.0 {
+                continue;
+            }
+            let factor = a[row][col];
+            for j in 0..n {
+                a[row][j] -= factor * a[col][j];
+            }
+            for j in 0..b[row].len() {
+                b[row][j] -= factor * b[col][j];
+            }
+        }
+    }
+    b
+}
+
+fn identity(n: usize) -> Vec<Vec<f64>> {
+    (0..n)
+        .map(|row| {
+            (0..n)
+                .map(|col| if row == col { 1.0 } else { 0.0 })
+                .collect()
+        })
+        .collect()
+}
+
+pub fn inverse(els: &[Vec<Object>]) -> Object {
+    let n = square_size("inverse", els);
+    check_invertible(els);
+    from_floats(gauss_jordan(float_matrix(els), identity(n)))
+}
+
+/// Solves `a x = b`. `b` is a list of numbers, giving `x` as a list, or a
+/// matrix with one right-hand side per column.
+pub fn solve(a: &[Vec<Object>], b: &Object) -> Object {
+    let n = square_size("solve", a);
+    check_invertible(a);
+    match b {
+        Object::List(els) => {
+            if els.len() != n {
+                panic!("solve expects b to have {} rows, got {}", n, els.len());
+            }
+            let column: Vec<Vec<Object>> = els.iter().map(|el| vec![el.clone()]).collect();
+            let x = gauss_jordan(float_matrix(a), float_matrix(&column));
+            Object::List(x.into_iter().map(|row| Object::Float(row[0])).collect())
+        }
+        Object::Array(els) => {
+            let (rows, _) = dimensions(els);
+            if rows != n {
+                panic!("solve expects b to have {} rows, got {}", n, rows);
+            }
+            from_floats(gauss_jordan(float_matrix(a), float_matrix(els)))
+        }
+        other => panic!("solve expects b to be a list or a matrix, got {:?}", other),
+    }
+}
+
+fn decomposition(parts: Vec<(&str, Object)>) -> Object {
+    let mut map = BTreeMap::new();
+    for (name, part) in parts {
+        map.insert(MapKey::String(name.to_string()), part);
+    }
+    Object::Map(map)
+}
+
+/// LU decomposition with partial pivoting: a map with a unit lower triangular
+/// `L`, an upper triangular `U` and a permutation matrix `P` such that
+/// `P @ A` equals `L @ U`.
+pub fn lu(els: &[Vec<Object>]) -> Object {
+    let n = square_size("lu", els);
+    let mut u = float_matrix(els);
+    let mut l = vec![vec![0.0; n]; n];
+    let mut perm: Vec<usize> = (0..n).collect();
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|&x, &y| u[x][col].abs().total_cmp(&u[y][col].abs()))
+            .unwrap();
+        u.swap(pivot, col);
+        l.swap(pivot, col);
+        perm.swap(pivot, col);
+        l[col][col] = 1.0;
+        if u[col][col].abs() < EPSILON {
+            continue;
+        }
+        for row in col + 1..n {
+            let factor = u[row][col] / u[col][col];
+            l[row][col] = factor;
+            for j in col..n {
+                u[row][j] -= factor * u[col][j];
+            }
+        }
+    }
+    let p = perm
+        .iter()
+        .map(|&from| {
+            (0..n)
+                .map(|col| Object::Integer((col == from) as i64))
+                .collect()
+        })
+        .collect();
+    decomposition(vec![
+        ("L", from_floats(l)),
+        ("P", Object::Array(p)),
+        ("U", from_floats(u)),
+    ])
+}
+
+/// Reduced QR decomposition of an `m x n` matrix with `m >= n` by modified
+/// Gram-Schmidt: a map with `Q`, whose columns are orthonormal, and an upper
+/// triangular `R` such that `Q @ R` equals `A`.
+pub fn qr(els: &[Vec<Object>]) -> Object {
+    let (rows, cols) = dimensions(els);
+    if rows < cols {
+        panic!(
+            "qr requires at least as many rows as columns, got {}x{}",
+            rows, cols
+        );
+    }
+    let a = float_matrix(els);
+    let mut q: Vec<Vec<f64>> = (0..cols)
+        .map(|col| (0..rows).map(|row| a[row][col]).collect())
+        .collect();
+    let mut r = vec![vec![0.0; cols]; cols];
+    for i in 0..cols {
+        let norm = q[i].iter().map(|el| el * el).sum::<f64>().sqrt();
+        if norm < EPSILON {
+            panic!("qr requires linearly independent columns");
+        }
+        r[i][i] = norm;
+        q[i].iter_mut().for_each(|el| *el /= norm);
+        for j in i + 1..cols {
+            let dot: f64 = q[i].iter().zip(&q[j]).map(|(x, y)| x * y).sum();
+            r[i][j] = dot;
+            for row in 0..rows {
+                q[j][row] -= dot * q[i][row];
+            }
+        }
+    }
+    let q = (0..rows)
+        .map(|row| (0..cols).map(|col| q[col][row]).collect())
+        .collect();
+    decomposition(vec![("Q", from_floats(q)), ("R", from_floats(r))])
+}
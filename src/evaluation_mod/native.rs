@@ -0,0 +1,145 @@
+use crate::error::Error;
+use crate::evaluation_mod::convert::FromArgs;
+use crate::evaluation_mod::evaluate::Object;
+use std::convert::TryInto;
+use std::fmt;
+use std::rc::Rc;
+
+/// The type a native function accepts for one of its arguments.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParamType {
+    Any,
+    Integer,
+    Float,
+    /// An integer or a float.
+    Number,
+    String,
+    Boolean,
+    List,
+    Matrix,
+    Tensor,
+    Map,
+    Function,
+}
+
+impl ParamType {
+    pub fn accepts(&self, arg: &Object) -> bool {
+        matches!(
+            (self, arg),
+            (ParamType::Any, _)
+                | (ParamType::Integer, Object::Integer(_))
+                | (ParamType::Float, Object::Float(_))
+                | (ParamType::Number, Object::Integer(_) | Object::Float(_))
+                | (ParamType::String, Object::String(_))
+                | (ParamType::Boolean, Object::Boolean(_))
+                | (ParamType::List, Object::List(_))
+                | (ParamType::Matrix, Object::Array(_))
+                | (ParamType::Tensor, Object::Tensor(..))
+                | (ParamType::Map, Object::Map(_))
+                | (ParamType::Function, Object::Function(..))
+        )
+    }
+}
+
+impl fmt::Display for ParamType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParamType::Any => write!(f, "any value"),
+            ParamType::Integer => write!(f, "an integer"),
+            ParamType::Float => write!(f, "a float"),
+            ParamType::Number => write!(f, "a number"),
+            ParamType::String => write!(f, "a string"),
+            ParamType::Boolean => write!(f, "a boolean"),
+            ParamType::List => write!(f, "a list"),
+            ParamType::Matrix => write!(f, "a matrix"),
+            ParamType::Tensor => write!(f, "a tensor"),
+            ParamType::Map => write!(f, "a map"),
+            ParamType::Function => write!(f, "a function"),
+        }
+    }
+}
+
+/// The Rust side of a native function.
+type NativeFn = Rc<dyn Fn(&[Object]) -> Object>;
+
+/// A function implemented in Rust by the host application and registered on
+/// an `Env` with `Env::register`. Calls are checked against `params` before
+/// they reach `func`, which can rely on the argument count and types.
+#[derive(Clone)]
+pub struct NativeFunction {
+    pub name: String,
+    pub params: Vec<ParamType>,
+    pub doc: String,
+    func: NativeFn,
+}
+
+impl NativeFunction {
+    pub fn new<F>(name: &str, params: Vec<ParamType>, doc: &str, func: F) -> Self
+    where
+        F: Fn(&[Object]) -> Object + 'static,
+    {
+        NativeFunction {
+            name: name.to_string(),
+            params,
+            doc: doc.to_string(),
+            func: Rc::new(func),
+        }
+    }
+
+    /// A native function whose parameters are given by the argument type of
+    /// `func`, e.g. `|(m, k): (Vec<Vec<i64>>, i64)| ...`. Arguments that do not
+    /// convert to those types are rejected before `func` is called, and so is
+    /// a result that does not convert to a value, such as ragged matrix rows.
+    pub fn typed<Args, R, F>(name: &str, doc: &str, func: F) -> Self
+    where
+        Args: FromArgs,
+        R: TryInto<Object>,
+        R::Error: fmt::Display,
+        F: Fn(Args) -> R + 'static,
+    {
+        let label = name.to_string();
+        NativeFunction::new(name, vec![ParamType::Any; Args::ARITY], doc, move |args| {
+            match Args::from_args(args) {
+                Ok(args) => match func(args).try_into() {
+                    Ok(result) => result,
+                    Err(err) => panic!("Invalid result from {}: {}", label, err),
+                },
+                Err(Error::Conversion(msg)) => panic!("Invalid arguments to {}: {}", label, msg),
+                Err(err) => panic!("{}", err),
+            }
+        })
+    }
+
+    pub fn arity(&self) -> usize {
+        self.params.len()
+    }
+
+    pub fn call(&self, args: &[Object]) -> Object {
+        if args.len() != self.arity() {
+            panic!(
+                "{} expects {} arguments, got {}",
+                self.name,
+                self.arity(),
+                args.len()
+            );
+        }
+        for (i, (param, arg)) in self.params.iter().zip(args).enumerate() {
+            if !param.accepts(arg) {
+                panic!(
+                    "Argument {} of {} must be {}, got {:?}",
+                    i + 1,
+                    self.name,
+                    param,
+                    arg
+                );
+            }
+        }
+        (self.func)(args)
+    }
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "NativeFunction({}/{})", self.name, self.arity())
+    }
+}
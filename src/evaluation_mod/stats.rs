@@ -0,0 +1,142 @@
+use crate::evaluation_mod::evaluate::Object;
+use crate::evaluation_mod::tensor;
+
+/// The statistics that take an optional axis, besides `sum`, `max` and `min`.
+pub const STATISTICS: [&str; 8] = [
+    "mean", "median", "variance", "stddev", "argmax", "argmin", "cumsum", "prod",
+];
+
+pub fn number_value(object: &Object) -> f64 {
+    match object {
+        Object::Integer(num) => *num as f64,
+        Object::Float(num) => *num,
+        other => panic!("Expected a number, got {:?}", other),
+    }
+}
+
+fn add(lhs: &Object, rhs: &Object) -> Object {
+    match (lhs, rhs) {
+        (Object::Integer(num1), Object::Integer(num2)) => Object::Integer(num1 + num2),
+        _ => Object::Float(number_value(lhs) + number_value(rhs)),
+    }
+}
+
+fn multiply(lhs: &Object, rhs: &Object) -> Object {
+    match (lhs, rhs) {
+        (Object::Integer(num1), Object::Integer(num2)) => Object::Integer(num1 * num2),
+        _ => Object::Float(number_value(lhs) * number_value(rhs)),
+    }
+}
+
+/// The position of the first largest (or, for `argmin`, smallest) value.
+fn best_position(name: &str, values: &[Object]) -> usize {
+    let mut best = 0;
+    for (i, value) in values.iter().enumerate() {
+        let (num, best_num) = (number_value(value), number_value(&values[best]));
+        let better = match name {
+            "max" | "argmax" => num > best_num,
+            _ => num < best_num,
+        };
+        if better {
+            best = i;
+        }
+    }
+    best
+}
+
+/// Population variance.
+fn variance(nums: &[f64]) -> f64 {
+    let mean = nums.iter().sum::<f64>() / nums.len() as f64;
+    nums.iter().map(|num| (num - mean).powi(2)).sum::<f64>() / nums.len() as f64
+}
+
+/// Reduces one row, column or whole matrix to a single value.
+fn reduce_lane(name: &str, values: &[Object]) -> Object {
+    match name {
+        "sum" => values
+            .iter()
+            .fold(Object::Integer(0), |sum, el| add(&sum, el)),
+        "prod" => values
+            .iter()
+            .fold(Object::Integer(1), |prod, el| multiply(&prod, el)),
+        _ if values.is_empty() => panic!("{} of an empty matrix", name),
+        "max" | "min" => values[best_position(name, values)].clone(),
+        "argmax" | "argmin" => Object::Integer(best_position(name, values) as i64),
+        _ => {
+            let mut nums: Vec<f64> = values.iter().map(number_value).collect();
+            match name {
+                "mean" => Object::Float(nums.iter().sum::<f64>() / nums.len() as f64),
+                // NaN has no place in the order, so it has no median.
+                "median" if nums.iter().any(|num| num.is_nan()) => {
+                    panic!("median of values containing NaN")
+                }
+                "median" => {
+                    nums.sort_by(f64::total_cmp);
+                    let mid = nums.len() / 2;
+                    if nums.len().is_multiple_of(2) {
+                        Object::Float((nums[mid - 1] + nums[mid]) / 2.0)
+                    } else {
+                        Object::Float(nums[mid])
+                    }
+                }
+                "variance" => Object::Float(variance(&nums)),
+                "stddev" => Object::Float(variance(&nums).sqrt()),
+                _ => panic!("Unknown statistic {}", name),
+            }
+        }
+    }
+}
+
+fn cumsum(values: &[Object]) -> Vec<Object> {
+    let mut sum = Object::Integer(0);
+    values
+        .iter()
+        .map(|el| {
+            sum = add(&sum, el);
+            sum.clone()
+        })
+        .collect()
+}
+
+/// Applies a statistic to a list, giving a single value, or a list for
+/// `cumsum`.
+pub fn reduce_list(name: &str, els: &[Object]) -> Object {
+    match name {
+        "cumsum" => Object::List(cumsum(els)),
+        _ => reduce_lane(name, els),
+    }
+}
+
+/// Applies a statistic to a whole matrix, or with `axis` 0 to every column
+/// and with `axis` 1 to every row. Per-column results form a single row and
+/// per-row results a single column, so they broadcast back against the
+/// matrix; `cumsum` keeps the shape of the matrix. Without an axis, `argmax`
+/// and `argmin` give the row-major position `i`, the cell
+/// `m[i / cols, i % cols]`.
+pub fn reduce(name: &str, els: &[Vec<Object>], axis: Option<i64>) -> Object {
+    let (shape, data) = tensor::from_matrix(els);
+    let (rows, cols) = (shape[0], shape[1]);
+    let columns = || -> Vec<Vec<Object>> {
+        (0..cols)
+            .map(|col| (0..rows).map(|row| els[row][col].clone()).collect())
+            .collect()
+    };
+    match (name, axis) {
+        (_, None) => reduce_list(name, &data),
+        ("cumsum", Some(0)) => {
+            let sums: Vec<Vec<Object>> = columns().iter().map(|col| cumsum(col)).collect();
+            Object::Array(
+                (0..rows)
+                    .map(|row| (0..cols).map(|col| sums[col][row].clone()).collect())
+                    .collect(),
+            )
+        }
+        ("cumsum", Some(1)) => Object::Array(els.iter().map(|row| cumsum(row)).collect()),
+        (_, Some(0)) => Object::Array(vec![columns()
+            .iter()
+            .map(|col| reduce_lane(name, col))
+            .collect()]),
+        (_, Some(1)) => Object::Array(els.iter().map(|row| vec![reduce_lane(name, row)]).collect()),
+        (_, Some(axis)) => panic!("axis must be 0 or 1, got {}", axis),
+    }
+}
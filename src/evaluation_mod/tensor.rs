@@ -0,0 +1,186 @@
+use crate::evaluation_mod::evaluate::Object;
+
+/// Reads a shape such as `[2, 3, 4]` from a list of non-negative integers.
+pub fn shape_from_object(shape: &Object) -> Vec<usize> {
+    match shape {
+        Object::List(dims) => dims
+            .iter()
+            .map(|dim| match dim {
+                Object::Integer(num) if *num >= 0 => *num as usize,
+                _ => panic!("Tensor shape must be a list of non-negative integers"),
+            })
+            .collect(),
+        _ => panic!("Tensor shape must be a list of non-negative integers"),
+    }
+}
+
+pub fn shape_to_object(shape: &[usize]) -> Object {
+    Object::List(
+        shape
+            .iter()
+            .map(|dim| Object::Integer(*dim as i64))
+            .collect(),
+    )
+}
+
+pub fn element_count(shape: &[usize]) -> usize {
+    shape.iter().product()
+}
+
+/// A tensor of `shape` with every element set to `value`.
+pub fn filled(shape: Vec<usize>, value: Object) -> Object {
+    let data = vec![value; element_count(&shape)];
+    Object::Tensor(shape, data)
+}
+
+/// Lays `data` out in `shape`, which must hold exactly as many elements.
+pub fn reshape(data: Vec<Object>, shape: Vec<usize>) -> Object {
+    if element_count(&shape) != data.len() {
+        panic!(
+            "Cannot reshape {} elements into shape {:?}",
+            data.len(),
+            shape
+        );
+    }
+    Object::Tensor(shape, data)
+}
+
+/// Resolves the indices of `t[i, j, ...]` against `shape`. Fewer indices
+/// than dimensions select a whole sub-tensor, so this returns the range of
+/// the selected elements in row-major storage and the shape of the selection,
+/// which is empty when every dimension was indexed.
+pub fn locate(shape: &[usize], indices: &[i64]) -> (std::ops::Range<usize>, Vec<usize>) {
+    if indices.len() > shape.len() {
+        panic!(
+            "Cannot index a rank {} tensor with {} indices",
+            shape.len(),
+            indices.len()
+        );
+    }
+    let mut offset = 0;
+    for (dim, (&index, &size)) in indices.iter().zip(shape.iter()).enumerate() {
+        if index < 0 || index as usize >= size {
+            panic!(
+                "Tensor index {} out of range for dimension {} of size {}",
+                index, dim, size
+            );
+        }
+        offset = offset * size + index as usize;
+    }
+    let rest = shape[indices.len()..].to_vec();
+    let len = element_count(&rest);
+    (offset * len..(offset + 1) * len, rest)
+}
+
+/// The shape and row-major elements of a rectangular matrix.
+pub fn from_matrix(els: &[Vec<Object>]) -> (Vec<usize>, Vec<Object>) {
+    let cols = els.first().map_or(0, |row| row.len());
+    if els.iter().any(|row| row.len() != cols) {
+        panic!("Array's rows need to be the same size");
+    }
+    let data = els.iter().flat_map(|row| row.iter().cloned()).collect();
+    (vec![els.len(), cols], data)
+}
+
+/// Turns a rank 2 tensor back into the `Vec<Vec<Object>>` matrix form.
+pub fn to_matrix(shape: &[usize], data: &[Object]) -> Vec<Vec<Object>> {
+    if shape.len() != 2 {
+        panic!("Expected a rank 2 tensor, got shape {:?}", shape);
+    }
+    if shape[1] == 0 {
+        return vec![vec![]; shape[0]];
+    }
+    data.chunks(shape[1]).map(|row| row.to_vec()).collect()
+}
+
+/// Writes `data` as nested lists, e.g. `[[1, 2], [3, 4]]`.
+pub fn write_nested(
+    f: &mut std::fmt::Formatter,
+    shape: &[usize],
+    data: &[Object],
+) -> std::fmt::Result {
+    if shape.is_empty() {
+        return write!(f, "{}", data[0].repr());
+    }
+    let len = element_count(&shape[1..]);
+    write!(f, "[")?;
+    for i in 0..shape[0] {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write_nested(f, &shape[1..], &data[i * len..(i + 1) * len])?;
+    }
+    write!(f, "]")
+}
+
+/// Picks elements of a tensor of `shape` by one list of positions per
+/// dimension. Returns their row-major offsets and the shape of the selection,
+/// which drops the dimensions marked as picked by a single index.
+pub fn select(shape: &[usize], axes: &[(Vec<usize>, bool)]) -> (Vec<usize>, Vec<usize>) {
+    let mut offsets = vec![0];
+    for (&len, (positions, _)) in shape.iter().zip(axes) {
+        offsets = offsets
+            .iter()
+            .flat_map(|offset| positions.iter().map(move |pos| offset * len + pos))
+            .collect();
+    }
+    let rest = axes
+        .iter()
+        .filter(|(_, kept)| *kept)
+        .map(|(positions, _)| positions.len())
+        .collect();
+    (offsets, rest)
+}
+
+/// Combines two tensors element by element with NumPy-style broadcasting:
+/// shapes are aligned at their last dimension, and a dimension of size 1, or
+/// one the shorter shape lacks, is repeated to match the other operand.
+pub fn broadcast<F>(
+    lhs: (&[usize], &[Object]),
+    rhs: (&[usize], &[Object]),
+    op: F,
+) -> (Vec<usize>, Vec<Object>)
+where
+    F: Fn(&Object, &Object) -> Object,
+{
+    let rank = lhs.0.len().max(rhs.0.len());
+    let pad = |shape: &[usize]| {
+        let mut padded = vec![1; rank - shape.len()];
+        padded.extend_from_slice(shape);
+        padded
+    };
+    let (left, right) = (pad(lhs.0), pad(rhs.0));
+    let shape: Vec<usize> = left
+        .iter()
+        .zip(&right)
+        .map(|(&l, &r)| {
+            if l == r || r == 1 {
+                l
+            } else if l == 1 {
+                r
+            } else {
+                panic!("Cannot broadcast shapes {:?} and {:?}", lhs.0, rhs.0)
+            }
+        })
+        .collect();
+    let data = (0..element_count(&shape))
+        .map(|flat| {
+            let (mut rest, mut l_offset, mut r_offset, mut l_stride, mut r_stride) =
+                (flat, 0, 0, 1, 1);
+            for dim in (0..rank).rev() {
+                let index = rest % shape[dim];
+                rest /= shape[dim];
+                if left[dim] != 1 {
+                    l_offset += index * l_stride;
+                }
+                if right[dim] != 1 {
+                    r_offset += index * r_stride;
+                }
+                l_stride *= left[dim];
+                r_stride *= right[dim];
+            }
+            op(&lhs.1[l_offset], &rhs.1[r_offset])
+        })
+        .collect();
+    (shape, data)
+}
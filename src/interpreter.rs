@@ -0,0 +1,369 @@
+use crate::error::Error;
+use crate::evaluation_mod::env::Env;
+use crate::evaluation_mod::evaluate::{eval_return, Object};
+use crate::evaluation_mod::native::NativeFunction;
+use crate::lexer_mod::lexer::Lexer;
+use crate::parser_mod::Parser::{Parser, Program};
+use std::any::Any;
+use std::cell::Cell;
+use std::fs;
+use std::io::BufRead;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::Once;
+
+thread_local! {
+    static EVALUATING: Cell<bool> = const { Cell::new(false) };
+}
+
+static QUIET_PANICS: Once = Once::new();
+
+/// Runtime errors are raised as panics inside the evaluator. While an
+/// `Interpreter` is evaluating, they are caught and returned as
+/// `Error::Runtime`, so the default hook must not print them to stderr.
+fn install_panic_hook() {
+    QUIET_PANICS.call_once(|| {
+        let default_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if !EVALUATING.with(|evaluating| evaluating.get()) {
+                default_hook(info);
+            }
+        }));
+    });
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(msg) => *msg,
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(msg) => msg.to_string(),
+            Err(_) => "unknown error".to_string(),
+        },
+    }
+}
+
+/// Parses `source`, reporting input that stops mid-statement as
+/// `Error::Incomplete` and any other parse errors as `Error::Parse`.
+pub(crate) fn parse(source: &str) -> Result<Program, Error> {
+    let mut parser = Parser::new(Lexer::new(source));
+    let program = parser.parse();
+    let errors = parser.get_errors();
+    if errors.is_empty() {
+        return Ok(program);
+    }
+    let messages = errors.iter().map(|err| err.to_string()).collect();
+    if errors.iter().all(|err| err.is_incomplete()) {
+        Err(Error::Incomplete(messages))
+    } else {
+        Err(Error::Parse(messages))
+    }
+}
+
+/// An embeddable rakiu interpreter. Globals, struct declarations and native
+/// functions persist across calls to `eval_str` and `eval_file`.
+pub struct Interpreter {
+    env: Env,
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        install_panic_hook();
+        Interpreter { env: Env::new() }
+    }
+
+    /// Rejects non-boolean `if` conditions instead of applying truthiness.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.env.strict = strict;
+    }
+
+    /// Evaluates `source` and returns the value of its last statement. After
+    /// a runtime error, globals assigned before the failure keep their values.
+    pub fn eval_str(&mut self, source: &str) -> Result<Object, Error> {
+        let program = parse(source)?;
+        let env = &mut self.env;
+        EVALUATING.with(|evaluating| evaluating.set(true));
+        let result = panic::catch_unwind(AssertUnwindSafe(|| eval_return(program, env)));
+        EVALUATING.with(|evaluating| evaluating.set(false));
+        result.map_err(|payload| {
+            // Blocks left by the error did not get to drop their variables.
+            env.exit_all_scopes();
+            Error::Runtime(panic_message(payload))
+        })
+    }
+
+    pub fn eval_file<P: AsRef<Path>>(&mut self, path: P) -> Result<Object, Error> {
+        let path = path.as_ref();
+        match fs::read_to_string(path) {
+            Ok(source) => self.eval_str(&source),
+            Err(err) => Err(Error::Io(format!("{}: {}", path.display(), err))),
+        }
+    }
+
+    /// Lets scripts read and write files inside `dir`. Without this, the file
+    /// builtins refuse every path.
+    pub fn allow_dir<P: AsRef<Path>>(&mut self, dir: P) -> Result<(), Error> {
+        let dir = dir.as_ref();
+        self.env
+            .allow_dir(dir)
+            .map_err(|err| Error::Io(format!("{}: {}", dir.display(), err)))
+    }
+
+    /// Makes `input`, `read_int` and `read_matrix` read from `reader` instead
+    /// of standard input.
+    pub fn set_input<R: BufRead + 'static>(&mut self, reader: R) {
+        self.env.set_input(reader);
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Object> {
+        self.env.env.get(name).cloned()
+    }
+
+    /// The names of the functions defined by the evaluated source, sorted.
+    pub fn function_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .env
+            .env
+            .iter()
+            .filter(|(_, value)| matches!(value, Object::Function(..)))
+            .map(|(name, _)| name.clone())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Binds `name` in the program's scope. A matrix is built from its rows
+    /// with `Object::try_from` first, since the rows may be ragged.
+    pub fn set_global<T: Into<Object>>(&mut self, name: &str, value: T) {
+        self.env.set(name.to_string(), value.into());
+    }
+
+    pub fn register(&mut self, function: NativeFunction) {
+        self.env.register(function);
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Interpreter::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluation_mod::native::ParamType;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn test_eval_str() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(interpreter.eval_str("1 + 2;"), Ok(Object::Integer(3)));
+        interpreter.eval_str("let m = [{1, 2} {3, 4}];").unwrap();
+        let result = interpreter.eval_str("m @ m;").unwrap();
+        assert_eq!(
+            Vec::<Vec<i64>>::try_from(result),
+            Ok(vec![vec![7, 10], vec![15, 22]])
+        );
+    }
+
+    #[test]
+    fn test_globals() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_global("scale", 3);
+        interpreter.set_global("m", Object::try_from(vec![vec![1, 2], vec![3, 4]]).unwrap());
+        interpreter.set_global("flag", true);
+        let result = interpreter.eval_str("if (flag) { m * scale }").unwrap();
+        assert_eq!(
+            Vec::<Vec<i64>>::try_from(result),
+            Ok(vec![vec![3, 6], vec![9, 12]])
+        );
+        interpreter.eval_str("let total = sum(m);").unwrap();
+        assert_eq!(
+            interpreter.get_global("total").map(i64::try_from),
+            Some(Ok(10))
+        );
+        assert_eq!(interpreter.get_global("missing"), None);
+        assert!(bool::try_from(Object::Integer(1)).is_err());
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            interpreter.eval_str("let x = ;"),
+            Err(Error::Parse(vec![
+                "Unexpected Token!: no prefix for Semicolon found".to_string()
+            ]))
+        );
+        assert_eq!(
+            interpreter.eval_str("fn f(x) {\n  x +"),
+            Err(Error::Incomplete(vec![
+                "Unexpected End of Input!: no prefix for Eof found".to_string(),
+                "Unexpected End of Input!: expected }, but found Eof instead".to_string()
+            ]))
+        );
+        assert!(matches!(
+            interpreter.eval_str("match (1) {"),
+            Err(Error::Incomplete(_))
+        ));
+        assert!(matches!(
+            interpreter.eval_str("let x = ; {"),
+            Err(Error::Parse(_))
+        ));
+        assert_eq!(
+            interpreter.eval_str("[1, 2][5];"),
+            Err(Error::Runtime(
+                "List index 5 out of range for length 2".to_string()
+            ))
+        );
+        assert_eq!(interpreter.eval_str("2 * 21;"), Ok(Object::Integer(42)));
+        assert!(interpreter
+            .eval_str("let y = 1; { let y = 2; [1][5] }")
+            .is_err());
+        assert_eq!(interpreter.eval_str("y;"), Ok(Object::Integer(1)));
+        match interpreter.eval_file("/nonexistent/formula.rk") {
+            Err(Error::Io(msg)) => assert!(msg.starts_with("/nonexistent/formula.rk")),
+            other => panic!("Expected an IO error, got {:?}", other),
+        }
+    }
+
+    fn scratch_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("rakiu-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_file_builtins() {
+        let dir = scratch_dir("files");
+        let mut interpreter = Interpreter::new();
+        interpreter.allow_dir(&dir).unwrap();
+        interpreter.set_global("dir", dir.display().to_string());
+        interpreter
+            .eval_str(
+                r#"write_csv(format("{}/m.csv", dir), [{1, 2.5, "a, b"} {3, 4, "say \"hi\""}]);
+                   write_file(format("{}/notes.txt", dir), "one\ntwo\n");"#,
+            )
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("m.csv")).unwrap(),
+            "1,2.5,\"a, b\"\n3,4,\"say \"\"hi\"\"\"\n"
+        );
+        assert_eq!(
+            interpreter
+                .eval_str(r#"read_csv(format("{}/m.csv", dir));"#)
+                .map(|m| m.to_string()),
+            Ok(r#"[{1, 2.5, "a, b"} {3, 4, "say \"hi\""}]"#.to_string())
+        );
+        assert_eq!(
+            interpreter.eval_str(r#"read_lines(format("{}/notes.txt", dir));"#),
+            Ok(Object::from(vec!["one".to_string(), "two".to_string()]))
+        );
+        assert_eq!(
+            interpreter.eval_str(r#"read_file(format("{}/../notes.txt", dir));"#),
+            Err(Error::Runtime(format!(
+                "Access to {}/../notes.txt is not allowed",
+                dir.display()
+            )))
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_file_access_denied_by_default() {
+        let dir = scratch_dir("denied");
+        fs::write(dir.join("secret.txt"), "secret").unwrap();
+        let path = dir.join("secret.txt").display().to_string();
+        let mut interpreter = Interpreter::new();
+        interpreter.set_global("path", path.clone());
+        assert_eq!(
+            interpreter.eval_str("read_file(path);"),
+            Err(Error::Runtime(format!("Access to {} is not allowed", path)))
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_file_access_through_symlinks() {
+        use std::os::unix::fs::symlink;
+        let dir = scratch_dir("links");
+        let outside = scratch_dir("links-outside");
+        fs::write(outside.join("secret.txt"), "secret").unwrap();
+        symlink(outside.join("secret.txt"), dir.join("secret.txt")).unwrap();
+        symlink(outside.join("created.txt"), dir.join("dangling.txt")).unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.allow_dir(&dir).unwrap();
+        interpreter.set_global("dir", dir.display().to_string());
+        assert_eq!(
+            interpreter.eval_str(r#"read_file(format("{}/secret.txt", dir));"#),
+            Err(Error::Runtime(format!(
+                "Access to {}/secret.txt is not allowed",
+                dir.display()
+            )))
+        );
+        assert_eq!(
+            interpreter.eval_str(r#"write_file(format("{}/dangling.txt", dir), "x");"#),
+            Err(Error::Runtime(format!(
+                "Access to {}/dangling.txt is not allowed",
+                dir.display()
+            )))
+        );
+        assert!(!outside.join("created.txt").exists());
+        fs::remove_dir_all(&dir).unwrap();
+        fs::remove_dir_all(&outside).unwrap();
+    }
+
+    #[test]
+    fn test_register_typed() {
+        let mut interpreter = Interpreter::new();
+        interpreter.register(NativeFunction::typed(
+            "scale_rows",
+            "scale_rows(m, k): every row of m times k.",
+            |(m, k): (Vec<Vec<i64>>, i64)| {
+                m.into_iter()
+                    .map(|row| row.into_iter().map(|el| el * k).collect())
+                    .collect::<Vec<Vec<i64>>>()
+            },
+        ));
+        assert_eq!(
+            interpreter.eval_str("scale_rows([{1, 2} {3, 4}], 2);"),
+            Ok(Object::try_from(vec![vec![2, 4], vec![6, 8]]).unwrap())
+        );
+        assert_eq!(
+            interpreter.eval_str("scale_rows([{1, 2}], true);"),
+            Err(Error::Runtime(
+                "Invalid arguments to scale_rows: argument 2: expected an integer, got Boolean(true)"
+                    .to_string()
+            ))
+        );
+        interpreter.register(NativeFunction::typed(
+            "triangle",
+            "triangle(n): rows 1, 1 2, ... up to n.",
+            |(n,): (i64,)| {
+                (1..=n)
+                    .map(|len| (1..=len).collect())
+                    .collect::<Vec<Vec<i64>>>()
+            },
+        ));
+        assert_eq!(
+            interpreter.eval_str("triangle(2);"),
+            Err(Error::Runtime(
+                "Invalid result from triangle: Conversion error: matrix rows must have the same length, got 1 and 2"
+                    .to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_register() {
+        let mut interpreter = Interpreter::new();
+        interpreter.register(NativeFunction::new(
+            "twice",
+            vec![ParamType::Integer],
+            "twice(n): 2 * n.",
+            |args| Object::Integer(2 * i64::try_from(args[0].clone()).unwrap()),
+        ));
+        assert_eq!(interpreter.eval_str("twice(21);"), Ok(Object::Integer(42)));
+    }
+}
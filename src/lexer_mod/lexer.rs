@@ -1,5 +1,5 @@
 use super::token;
-use super::token::Token;
+use super::token::{Span, Token};
 use std::str::Chars;
 use std::iter::Peekable;
 
@@ -7,17 +7,40 @@ fn is_letter(ch: char) -> bool {
     ch.is_alphabetic() || ch == '_'
 }
 
+#[derive(Clone)]
 pub struct Lexer<'a> {
     input: Peekable<Chars<'a>>,
+    line: usize,
+    column: usize,
+    token_start: Span,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(input: &str) -> Lexer {
-        Lexer { input: input.chars().peekable() }
+        Lexer {
+            input: input.chars().peekable(),
+            line: 1,
+            column: 1,
+            token_start: Span { line: 1, column: 1 },
+        }
     }
 
     pub fn read_char(&mut self) -> Option<char> {
-        self.input.next()
+        let ch = self.input.next();
+        match ch {
+            Some('\n') => {
+                self.line += 1;
+                self.column = 1;
+            }
+            Some(_) => self.column += 1,
+            None => (),
+        }
+        ch
+    }
+
+    /// Where the token last returned by `next_token` starts.
+    pub fn token_start(&self) -> Span {
+        self.token_start
     }
 
     pub fn peek_char(&mut self) -> Option<&char> {
@@ -57,27 +80,60 @@ impl<'a> Lexer<'a> {
         ident
     }
 
-    fn read_number(&mut self, first: char) -> i64 {
-        let mut number = String::new();
-        number.push(first);
-
+    fn read_digits(&mut self, number: &mut String) {
         while let Some(&c) = self.peek_char() {
             if !c.is_numeric() {
                 break;
             }
             number.push(self.read_char().unwrap());
         }
+    }
+
+    // A `.` only continues a number when a digit follows it, so `1.5` is a
+    // float while `x.1` and `1.field` still lex the dot on its own.
+    fn read_number(&mut self, first: char) -> Token {
+        let mut number = String::new();
+        number.push(first);
+        self.read_digits(&mut number);
+
+        let mut ahead = self.input.clone();
+        if ahead.next() == Some('.') && ahead.peek().is_some_and(|c| c.is_numeric()) {
+            number.push(self.read_char().unwrap());
+            self.read_digits(&mut number);
+            return Token::Float(number.parse().unwrap());
+        }
         let ret_number: i64 = number.parse().unwrap();
-        ret_number
+        Token::Int(ret_number)
+    }
+
+    fn read_string(&mut self) -> Option<String> {
+        let mut string = String::new();
+        loop {
+            match self.read_char() {
+                Some('"') => return Some(string),
+                Some('\\') => match self.read_char() {
+                    Some('n') => string.push('\n'),
+                    Some('t') => string.push('\t'),
+                    Some(ch) => string.push(ch),
+                    None => return None,
+                },
+                Some(ch) => string.push(ch),
+                None => return None,
+            }
+        }
     }
 
     pub fn next_token(&mut self) -> Token {
         self.skip_whitespace();
+        self.token_start = Span { line: self.line, column: self.column };
         match self.read_char() {
             Some('=') => {
                 if self.peek_char_eq('=') {
                     self.read_char();
                     Token::Equal
+                } else if self.peek_char_eq('>') {
+                    self.read_char();
+                    Token::FatArrow
                 } else {
                     Token::Assign
                 }
@@ -94,6 +150,9 @@ impl<'a> Lexer<'a> {
                 if self.peek_char_eq('+') {
                     self.read_char();
                     Token::Increment
+                } else if self.peek_char_eq('=') {
+                    self.read_char();
+                    Token::PlusAssign
                 } else {
                     Token::Plus
                 }
@@ -102,6 +161,9 @@ impl<'a> Lexer<'a> {
                 if self.peek_char_eq('-') {
                     self.read_char();
                     Token::Decrement
+                } else if self.peek_char_eq('=') {
+                    self.read_char();
+                    Token::MinusAssign
                 } else {
                     Token::Minus
                 }
@@ -110,6 +172,9 @@ impl<'a> Lexer<'a> {
                 if self.peek_char_eq('=') {
                     self.read_char();
                     Token::MoreThanAndEqual
+                } else if self.peek_char_eq('>') {
+                    self.read_char();
+                    Token::ShiftRight
                 } else {
                     Token::MoreThan
                 }
@@ -118,16 +183,55 @@ impl<'a> Lexer<'a> {
                 if self.peek_char_eq('=') {
                     self.read_char();
                     Token::LessThanAndEqual
+                } else if self.peek_char_eq('<') {
+                    self.read_char();
+                    Token::ShiftLeft
                 } else {
                     Token::LessThan
                 }
             }
 
             Some('/') => {
-                Token::Slash
+                if self.peek_char_eq('=') {
+                    self.read_char();
+                    Token::SlashAssign
+                } else {
+                    Token::Slash
+                }
             }
             Some('*') => {
-                Token::Asterisk
+                if self.peek_char_eq('*') {
+                    self.read_char();
+                    Token::Power
+                } else if self.peek_char_eq('=') {
+                    self.read_char();
+                    Token::AsteriskAssign
+                } else {
+                    Token::Asterisk
+                }
+            }
+            Some('%') => {
+                if self.peek_char_eq('=') {
+                    self.read_char();
+                    Token::PercentAssign
+                } else {
+                    Token::Percent
+                }
+            }
+            Some('&') => {
+                Token::Ampersand
+            }
+            Some('|') => {
+                Token::Pipe
+            }
+            Some('^') => {
+                Token::Caret
+            }
+            Some('~') => {
+                Token::Tilde
+            }
+            Some('@') => {
+                Token::At
             }
             Some(';') => {
                 Token::Semicolon
@@ -135,6 +239,18 @@ impl<'a> Lexer<'a> {
             Some(',') => {
                 Token::Comma
             }
+            Some(':') => {
+                Token::Colon
+            }
+            Some('.') => {
+                Token::Dot
+            }
+            Some('"') => {
+                match self.read_string() {
+                    Some(string) => Token::Str(string),
+                    None => Token::Illegal,
+                }
+            }
             Some('(') => {
                 Token::LeftParanthesis
             }
@@ -158,7 +274,7 @@ impl<'a> Lexer<'a> {
                     let literal = self.read_identifier(ch);
                     token::get_identifier(&literal)
                 } else if ch.is_numeric() {
-                    Token::Int(self.read_number(ch))
+                    self.read_number(ch)
                 } else {
                     Token::Illegal
                 }
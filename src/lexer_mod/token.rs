@@ -1,6 +1,19 @@
 use std::fmt;
 
-#[derive(Debug, PartialEq)]
+/// Where a token starts in the source. Lines and columns count from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     //Special Tokens
     Eof,
@@ -9,8 +22,15 @@ pub enum Token {
     //Identifiers
     Identifier(String),
     Int(i64),
+    Float(f64),
+    Str(String),
     //Operators
     Assign,
+    PlusAssign,
+    MinusAssign,
+    AsteriskAssign,
+    SlashAssign,
+    PercentAssign,
     Equal,
     NotEqual,
     LessThan,
@@ -22,10 +42,22 @@ pub enum Token {
     Minus,
     Decrement,
     Asterisk,
+    Power,
     Slash,
+    Percent,
     Exclamation,
+    Ampersand,
+    Pipe,
+    Caret,
+    Tilde,
+    ShiftLeft,
+    ShiftRight,
+    At,
 
     //Delimiters
+    FatArrow,
+    Colon,
+    Dot,
     Comma,
     Semicolon,
     LeftParanthesis,
@@ -44,6 +76,8 @@ pub enum Token {
     If,
     Else,
     Return,
+    Match,
+    Struct,
     And,
     Or,
 }
@@ -61,6 +95,15 @@ impl fmt::Display for Token {
             Token::Minus => write!(f, "-"),
             Token::Slash => write!(f, "/"),
             Token::Asterisk => write!(f, "*"),
+            Token::Power => write!(f, "**"),
+            Token::Percent => write!(f, "%"),
+            Token::Ampersand => write!(f, "&"),
+            Token::Pipe => write!(f, "|"),
+            Token::Caret => write!(f, "^"),
+            Token::Tilde => write!(f, "~"),
+            Token::ShiftLeft => write!(f, "<<"),
+            Token::ShiftRight => write!(f, ">>"),
+            Token::At => write!(f, "@"),
             Token::Equal => write!(f, "=="),
             Token::NotEqual => write!(f, "!="),
             Token::MoreThanAndEqual => write!(f, ">="),
@@ -68,6 +111,14 @@ impl fmt::Display for Token {
             Token::LessThanAndEqual => write!(f, "<="),
             Token::LessThan => write!(f, "<"),
             Token::Assign => write!(f, "="),
+            Token::PlusAssign => write!(f, "+="),
+            Token::MinusAssign => write!(f, "-="),
+            Token::AsteriskAssign => write!(f, "*="),
+            Token::SlashAssign => write!(f, "/="),
+            Token::PercentAssign => write!(f, "%="),
+            Token::FatArrow => write!(f, "=>"),
+            Token::Colon => write!(f, ":"),
+            Token::Dot => write!(f, "."),
             Token::Comma => write!(f, ","),
             Token::Semicolon => write!(f, ";"),
             Token::LeftParanthesis => write!(f, "("),
@@ -95,6 +146,8 @@ pub fn get_identifier(idnt: &str) -> Token {
         "and" => Token::And,
         "or" => Token::Or,
         "return" => Token::Return,
+        "match" => Token::Match,
+        "struct" => Token::Struct,
         _ => Token::Identifier(idnt.to_string()),
     }
 }
@@ -0,0 +1,13 @@
+pub mod error;
+pub mod evaluation_mod;
+pub mod interpreter;
+pub mod lexer_mod;
+pub mod parser_mod;
+pub mod test_runner;
+
+pub use crate::error::Error;
+pub use crate::evaluation_mod::convert::{unpack, Element, FromArgs};
+pub use crate::evaluation_mod::evaluate::Object;
+pub use crate::evaluation_mod::native::{NativeFunction, ParamType};
+pub use crate::interpreter::Interpreter;
+pub use crate::test_runner::{run_tests, TestResult};
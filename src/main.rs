@@ -1,34 +1,99 @@
-mod evaluation_mod;
-mod lexer_mod;
-mod parser_mod;
-use crate::evaluation_mod::codegen::generate_code;
-use crate::evaluation_mod::env::Env;
-use crate::evaluation_mod::evaluate::eval_statements;
-use lexer_mod::lexer::Lexer;
-use parser_mod::Parser::Parser;
-use std::io;
+use rakiu::evaluation_mod::codegen::generate_code;
+use rakiu::{run_tests, Error, Interpreter};
+use std::env;
+use std::io::{self, BufRead, Write};
+use std::process;
 
 fn main() {
-    let mut input = String::new();
-    while input.trim() != "exit" {
-        io::stdin()
-            .read_line(&mut input)
-            .expect("Could not read from stdin");
-        let lexer = Lexer::new(&mut input);
-        let mut parser = Parser::new(lexer);
-        let program = parser.parse();
-        let errs = parser.get_errors();
-        // println!("{}", &program[program.len() - 1]);
+    let args: Vec<String> = env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("test") => match args.get(2) {
+            Some(dir) => test(dir),
+            None => {
+                eprintln!("usage: rakiu test <dir>");
+                process::exit(2);
+            }
+        },
+        _ => repl(args.iter().any(|arg| arg == "--strict")),
+    }
+}
+
+/// `rakiu test dir/`: runs the `test_*` functions of every `.rk` file under
+/// `dir` and exits with status 1 if any of them fails.
+fn test(dir: &str) {
+    let results = match run_tests(dir) {
+        Ok(results) => results,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(2);
+        }
+    };
+    let mut failed = 0;
+    for result in &results {
+        let name = format!("{}::{}", result.file.display(), result.name);
+        match &result.outcome {
+            Ok(()) => println!("test {} ... ok", name),
+            Err(err) => {
+                failed += 1;
+                println!("test {} ... FAILED\n    {}", name, err);
+            }
+        }
+    }
+    println!(
+        "\ntest result: {}. {} passed; {} failed",
+        if failed == 0 { "ok" } else { "FAILED" },
+        results.len() - failed,
+        failed
+    );
+    if failed > 0 {
+        process::exit(1);
+    }
+}
+
+fn repl(strict: bool) {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_strict(strict);
+    interpreter
+        .allow_dir(".")
+        .expect("Could not read the working directory");
 
-        if errs.len() > 0 {
-            for el in &errs {
-                println!("{}", el);
+    // Globals persist between inputs, and only the new input is evaluated.
+    // Input that ends in the middle of a statement, such as a function body
+    // spread over several lines, is kept until the statement is complete.
+    // While it is kept the prompt is `...`, and an empty line or `exit`
+    // drops it.
+    let mut pending = String::new();
+    loop {
+        if !pending.is_empty() {
+            print!("... ");
+            io::stdout().flush().expect("Could not write to stdout");
+        }
+        let mut line = String::new();
+        let read = io::stdin()
+            .lock()
+            .read_line(&mut line)
+            .expect("Could not read from stdin");
+        if read == 0 || (pending.is_empty() && line.trim() == "exit") {
+            break;
+        }
+        if !pending.is_empty() && (line.trim().is_empty() || line.trim() == "exit") {
+            pending.clear();
+            continue;
+        }
+        pending.push_str(&line);
+        match interpreter.eval_str(&pending) {
+            Err(Error::Incomplete(_)) => continue,
+            Err(Error::Parse(errs)) => {
+                for el in &errs {
+                    println!("{}", el);
+                }
             }
+            Err(err) => println!("{}", err),
+            Ok(_) => (),
         }
-        let mut env = Env::new();
         // unsafe {
         //     generate_code(program);
         // }
-        eval_statements(program, &mut env);
+        pending.clear();
     }
 }
@@ -1,4 +1,5 @@
-use crate::lexer_mod::token::Token;
+use crate::lexer_mod::token::{Span, Token};
+use std::collections::BTreeSet;
 use std::fmt;
 pub type Identifier = String;
 
@@ -17,6 +18,8 @@ impl Program {
 #[derive(Debug, Clone, PartialEq)]
 pub enum Statement {
     Let(Identifier, Expression),
+    /// `struct Point { x, y }` declares a record type and its fields.
+    Struct(Identifier, Vec<Identifier>),
     Return(Expression),
     Expression(Expression),
     None,
@@ -42,16 +45,58 @@ pub enum Expression {
     Bool(bool),
     Identifier(Identifier),
     Integer(i64),
+    Float(f64),
+    String(String),
+    /// `span` is where the call starts, i.e. the start of `func`.
     Call {
         func: Box<Expression>,
         args: Vec<Expression>,
+        span: Span,
     },
     Array(Vec<Vec<Expression>>),
+    List(Vec<Expression>),
+    Map(Vec<(Expression, Expression)>),
     Function(Identifier, Vec<Identifier>, Vec<Statement>),
     Infix(Infix, Box<Expression>, Box<Expression>),
     Prefix(Prefix, Box<Expression>),
-    Index(Box<Expression>, Box<Expression>, Box<Option<Expression>>),
+    Postfix(Postfix, Box<Expression>),
+    /// `target op= value`; `++target` and `--target` are `target += 1` and
+    /// `target -= 1`.
+    CompoundAssign(Infix, Box<Expression>, Box<Expression>),
+    /// `a[i]`, `m[i, j]` or `t[i, j, k, ...]`. Each index may be a `Slice`.
+    Index(Box<Expression>, Vec<Expression>),
+    /// `start:end:step` inside an index; any of the three may be left out.
+    Slice(
+        Option<Box<Expression>>,
+        Option<Box<Expression>>,
+        Option<Box<Expression>>,
+    ),
     If(Box<Expression>, Vec<Statement>, Option<Vec<Statement>>),
+    Block(Vec<Statement>),
+    Match(Box<Expression>, Vec<MatchArm>),
+    StructLiteral(Identifier, Vec<(Identifier, Expression)>),
+    Field(Box<Expression>, Identifier),
+}
+
+impl Expression {
+    /// Desugars `target op= value` into `target = target op value`. This
+    /// reads `target` twice, so the interpreter first replaces the index
+    /// operands of `target` by their values, and the LLVM backend applies it
+    /// to variables only.
+    pub fn compound_assign(infix: Infix, target: Expression, value: Expression) -> Expression {
+        Expression::Infix(
+            Infix::Assign,
+            Box::new(target.clone()),
+            Box::new(Expression::Infix(infix, Box::new(target), Box::new(value))),
+        )
+    }
+
+    pub fn is_assignable(&self) -> bool {
+        match self {
+            Expression::Identifier(_) | Expression::Index(..) | Expression::Field(..) => true,
+            _ => false,
+        }
+    }
 }
 
 impl fmt::Display for Expression {
@@ -76,29 +121,136 @@ impl fmt::Display for Expression {
                 }
                 writeln!(f, "")
             }
-            Expression::Index(ident, expr, index2) => {
-                write!(f, "{}", *ident)?;
-                // write!(f, "[{}]", *expr)
-                if *index2 != Box::new(None) {
-                    write!(f, "[{} {:?}]", *expr, *index2)
-                } else {
-                    write!(f, "[{}]", *expr)
+            Expression::Index(ident, indices) => {
+                write!(f, "{}[", *ident)?;
+                for (i, index) in indices.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", index)?;
                 }
+                write!(f, "]")
+            }
+            Expression::Slice(start, end, step) => {
+                if let Some(start) = start {
+                    write!(f, "{}", start)?;
+                }
+                write!(f, ":")?;
+                if let Some(end) = end {
+                    write!(f, "{}", end)?;
+                }
+                if let Some(step) = step {
+                    write!(f, ":{}", step)?;
+                }
+                Ok(())
             }
             Expression::Infix(inf, expr1, expr2) => {
                 write!(f, "\n\t\t\t{} {} {}", *expr1, inf, *expr2)
             }
+            Expression::Block(stmts) => {
+                write!(f, "Block: ")?;
+                for st in stmts {
+                    write!(f, "\n\t\t{} ", st)?;
+                }
+                writeln!(f, "")
+            }
             _ => write!(f, "{:?}", self),
         }
     }
 }
 
+/// One `pattern | pattern => body` arm of a match expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchArm {
+    pub patterns: Vec<Pattern>,
+    pub body: Expression,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    Wildcard,
+    Integer(i64),
+    Bool(bool),
+    Binding(Identifier),
+    /// `[{a, b} {c, d}]` matches an array of exactly that shape.
+    Array(Vec<Vec<Pattern>>),
+    /// `[a, b]` matches a list of exactly that length.
+    List(Vec<Pattern>),
+}
+
+impl Pattern {
+    /// Whether the pattern matches any value at all.
+    pub fn is_irrefutable(&self) -> bool {
+        match self {
+            Pattern::Wildcard | Pattern::Binding(_) => true,
+            _ => false,
+        }
+    }
+
+    /// The names the pattern binds, in name order.
+    pub fn bindings(&self) -> BTreeSet<Identifier> {
+        match self {
+            Pattern::Binding(ident) => std::iter::once(ident.clone()).collect(),
+            Pattern::Array(rows) => rows.iter().flatten().flat_map(Pattern::bindings).collect(),
+            Pattern::List(patterns) => patterns.iter().flat_map(Pattern::bindings).collect(),
+            _ => BTreeSet::new(),
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Pattern::Wildcard => write!(f, "_"),
+            Pattern::Integer(num) => write!(f, "{}", num),
+            Pattern::Bool(_bool) => write!(f, "{}", _bool),
+            Pattern::Binding(ident) => write!(f, "{}", ident),
+            Pattern::Array(rows) => {
+                write!(f, "[")?;
+                for (i, row) in rows.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{{")?;
+                    for (j, pattern) in row.iter().enumerate() {
+                        if j > 0 {
+                            write!(f, ", ")?;
+                        }
+                        write!(f, "{}", pattern)?;
+                    }
+                    write!(f, "}}")?;
+                }
+                write!(f, "]")
+            }
+            Pattern::List(patterns) => {
+                write!(f, "[")?;
+                for (i, pattern) in patterns.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", pattern)?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Infix {
     Plus,
     Minus,
     Divide,
     Multiply,
+    Modulo,
+    Power,
+    /// Matrix multiplication, `a @ b`.
+    MatMul,
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
     Equal,
     NotEqual,
     MoreThanAndEqual,
@@ -115,6 +267,14 @@ impl fmt::Display for Infix {
             Infix::Minus => write!(f, "-"),
             Infix::Divide => write!(f, "/"),
             Infix::Multiply => write!(f, "*"),
+            Infix::Modulo => write!(f, "%"),
+            Infix::Power => write!(f, "**"),
+            Infix::MatMul => write!(f, "@"),
+            Infix::BitAnd => write!(f, "&"),
+            Infix::BitOr => write!(f, "|"),
+            Infix::BitXor => write!(f, "^"),
+            Infix::ShiftLeft => write!(f, "<<"),
+            Infix::ShiftRight => write!(f, ">>"),
             Infix::Equal => write!(f, "=="),
             Infix::NotEqual => write!(f, "!="),
             Infix::MoreThanAndEqual => write!(f, ">="),
@@ -131,14 +291,41 @@ pub enum Prefix {
     Plus,
     Minus,
     Not,
+    BitNot,
 }
 
 impl fmt::Display for Prefix {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            Prefix::Plus => write!(f, "++"),
-            Prefix::Minus => write!(f, "--"),
+            Prefix::Plus => write!(f, "+"),
+            Prefix::Minus => write!(f, "-"),
             Prefix::Not => write!(f, "!"),
+            Prefix::BitNot => write!(f, "~"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Postfix {
+    Increment,
+    Decrement,
+}
+
+impl Postfix {
+    /// The arithmetic operator `x++`/`x--` applies before storing `x`.
+    pub fn infix(&self) -> Infix {
+        match *self {
+            Postfix::Increment => Infix::Plus,
+            Postfix::Decrement => Infix::Minus,
+        }
+    }
+}
+
+impl fmt::Display for Postfix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Postfix::Increment => write!(f, "++"),
+            Postfix::Decrement => write!(f, "--"),
         }
     }
 }
@@ -1,7 +1,7 @@
 use super::ParseItem;
 use crate::lexer_mod::lexer;
 use crate::lexer_mod::lexer::Lexer;
-use crate::lexer_mod::token::Token;
+use crate::lexer_mod::token::{Span, Token};
 
 use std::fmt;
 use std::mem;
@@ -12,25 +12,44 @@ pub type Program = Vec<ParseItem::Statement>;
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub enum Order {
     Lowest,
+    Assign,
     Equals,
     LessGreater,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Shift,
     Sum,
     Product,
+    MatMul,
     Prefix,
+    Power,
     Call,
     Index,
-    Assign,
 }
 
 #[derive(Debug, Clone)]
 pub enum Error {
     UnexpectedToken,
+    InvalidAssignmentTarget,
+    NonExhaustiveMatch,
+    /// The input ended before the construct being parsed was complete.
+    UnexpectedEof,
+    /// The `|` alternatives of a match arm bind different names.
+    MismatchedBindings,
+    /// A struct declaration lists the same field twice.
+    DuplicateField,
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             Error::UnexpectedToken => write!(f, "Unexpected Token!"),
+            Error::InvalidAssignmentTarget => write!(f, "Invalid Assignment Target!"),
+            Error::NonExhaustiveMatch => write!(f, "Non-exhaustive Match!"),
+            Error::UnexpectedEof => write!(f, "Unexpected End of Input!"),
+            Error::MismatchedBindings => write!(f, "Mismatched Bindings!"),
+            Error::DuplicateField => write!(f, "Duplicate Field!"),
         }
     }
 }
@@ -45,6 +64,11 @@ impl ParseError {
     fn new(kind: Error, msg: String) -> Self {
         ParseError { kind, msg }
     }
+
+    /// Whether more input could fix this error, as when a `{` is not closed.
+    pub fn is_incomplete(&self) -> bool {
+        matches!(self.kind, Error::UnexpectedEof)
+    }
 }
 
 impl fmt::Display for ParseError {
@@ -53,11 +77,18 @@ impl fmt::Display for ParseError {
     }
 }
 
+#[derive(Clone)]
 pub struct Parser<'a> {
     pub current_token: Box<Token>,
     pub next_token: Box<Token>,
+    /// Where `current_token` and `next_token` start in the source.
+    pub current_span: Span,
+    next_span: Span,
     lexer: Lexer<'a>,
     err_list: ParseErrors,
+    /// Reject `op=`, `++` and `--` on anything but a variable, for the LLVM
+    /// backend, which has no matrix, list or struct values to update.
+    variable_updates_only: bool,
 }
 
 impl<'a> Parser<'a> {
@@ -66,7 +97,10 @@ impl<'a> Parser<'a> {
             lexer: lexer_,
             current_token: Box::new(Token::Illegal),
             next_token: Box::new(Token::Illegal),
+            current_span: Span { line: 1, column: 1 },
+            next_span: Span { line: 1, column: 1 },
             err_list: Vec::new(),
+            variable_updates_only: false,
         };
         parser.next_token();
         parser.next_token();
@@ -74,26 +108,67 @@ impl<'a> Parser<'a> {
         parser
     }
 
+    /// A parser for programs compiled by the LLVM backend, which reports
+    /// updates of cells and fields such as `m[i, j] += 1` as parse errors.
+    pub fn for_llvm_backend(lexer_: Lexer<'a>) -> Parser<'a> {
+        let mut parser = Parser::new(lexer_);
+        parser.variable_updates_only = true;
+        parser
+    }
+
     pub fn get_errors(&mut self) -> ParseErrors {
         self.err_list.clone()
     }
 
+    /// The kind of error for finding `found` where something else belongs.
+    fn unexpected(found: &Token) -> Error {
+        match found {
+            Token::Eof => Error::UnexpectedEof,
+            _ => Error::UnexpectedToken,
+        }
+    }
+
     fn error_next(&mut self, tok: &Token) {
         self.err_list.push(ParseError::new(
-            Error::UnexpectedToken,
+            Self::unexpected(&self.next_token),
             format!("expected {}, but found {} instead", *tok, *self.next_token),
         ));
     }
 
     fn error_no_prefix(&mut self) {
         self.err_list.push(ParseError::new(
-            Error::UnexpectedToken,
+            Self::unexpected(&self.current_token),
             format!("no prefix for {:?} found", *self.current_token,),
         ));
     }
 
+    fn error_assignment_target(&mut self, target: &ParseItem::Expression) {
+        self.err_list.push(ParseError::new(
+            Error::InvalidAssignmentTarget,
+            format!("cannot assign to {}", target),
+        ));
+    }
+
+    /// Checks the target of `op=`, `++` or `--`, reporting an error if it
+    /// cannot be updated.
+    fn check_update_target(&mut self, target: &ParseItem::Expression) -> bool {
+        if !target.is_assignable() {
+            self.error_assignment_target(target);
+            return false;
+        }
+        if self.variable_updates_only && !matches!(target, ParseItem::Expression::Identifier(_)) {
+            self.err_list.push(ParseError::new(
+                Error::InvalidAssignmentTarget,
+                format!("the LLVM backend can only update variables, not {}", target),
+            ));
+            return false;
+        }
+        true
+    }
+
     pub fn next_token(&mut self) {
         self.current_token = mem::replace(&mut self.next_token, Box::new(self.lexer.next_token()));
+        self.current_span = mem::replace(&mut self.next_span, self.lexer.token_start());
     }
 
     fn next_token_is(&self, tok: &Token) -> bool {
@@ -117,11 +192,22 @@ impl<'a> Parser<'a> {
             | Token::LessThanAndEqual
             | Token::MoreThan
             | Token::MoreThanAndEqual => Order::LessGreater,
+            Token::Pipe => Order::BitOr,
+            Token::Caret => Order::BitXor,
+            Token::Ampersand => Order::BitAnd,
+            Token::ShiftLeft | Token::ShiftRight => Order::Shift,
             Token::Plus | Token::Minus => Order::Sum,
-            Token::Asterisk | Token::Slash => Order::Product,
-            Token::LeftBracket => Order::Index,
+            Token::Asterisk | Token::Slash | Token::Percent => Order::Product,
+            Token::At => Order::MatMul,
+            Token::Power => Order::Power,
+            Token::LeftBracket | Token::Dot | Token::Increment | Token::Decrement => Order::Index,
             Token::LeftParanthesis => Order::Call,
-            Token::Assign => Order::Assign,
+            Token::Assign
+            | Token::PlusAssign
+            | Token::MinusAssign
+            | Token::AsteriskAssign
+            | Token::SlashAssign
+            | Token::PercentAssign => Order::Assign,
             _ => Order::Lowest,
         }
     }
@@ -134,11 +220,24 @@ impl<'a> Parser<'a> {
         Self::token_order(&self.current_token)
     }
 
+    /// Parses a whole program. Statements are separated the same way at the
+    /// top level and inside `{ ... }` blocks:
+    ///
+    /// ```text
+    /// statement := "let" IDENT ("=" expression)? ";"
+    ///            | "return" expression ";"
+    ///            | expression ";"
+    /// ```
+    ///
+    /// The `;` may be left out after the last statement of a block or of the
+    /// program, and after expressions that end in a block (`if`, `fn` and
+    /// `{ ... }`). A block evaluates to the value of its last statement, so
+    /// `let x = { let y = 2; y * y };` binds 4.
     pub fn parse(&mut self) -> Program {
         let mut program: Program = vec![];
 
         while *self.current_token != Token::Eof {
-            match self.parse_statement() {
+            match self.parse_terminated_statement(&Token::Eof) {
                 Some(stmt) => {
                     program.push(stmt);
                 }
@@ -149,6 +248,59 @@ impl<'a> Parser<'a> {
         program
     }
 
+    /// Parses a statement and the `;` that ends it, leaving the current token
+    /// on the last token consumed. `end` is the token that closes the
+    /// enclosing statement list.
+    fn parse_terminated_statement(&mut self, end: &Token) -> Option<ParseItem::Statement> {
+        if *self.current_token == Token::Semicolon {
+            return None;
+        }
+
+        let statement = match self.parse_statement() {
+            Some(statement) => statement,
+            None => {
+                self.skip_statement(end);
+                return None;
+            }
+        };
+
+        if self.next_token_is(&Token::Semicolon) {
+            self.next_token();
+        } else if !self.next_token_is(end)
+            && !self.next_token_is(&Token::Eof)
+            && !Self::ends_with_block(&statement)
+        {
+            self.error_next(&Token::Semicolon);
+            self.skip_statement(end);
+        }
+        Some(statement)
+    }
+
+    fn ends_with_block(statement: &ParseItem::Statement) -> bool {
+        match statement {
+            ParseItem::Statement::Expression(ParseItem::Expression::If(..))
+            | ParseItem::Statement::Expression(ParseItem::Expression::Function(..))
+            | ParseItem::Statement::Expression(ParseItem::Expression::Block(..))
+            | ParseItem::Statement::Expression(ParseItem::Expression::Match(..))
+            | ParseItem::Statement::Struct(..) => true,
+            _ => false,
+        }
+    }
+
+    /// Error recovery: drops the rest of a malformed statement, up to and
+    /// including its `;`.
+    fn skip_statement(&mut self, end: &Token) {
+        while !self.next_token_is(&Token::Semicolon)
+            && !self.next_token_is(end)
+            && !self.next_token_is(&Token::Eof)
+        {
+            self.next_token();
+        }
+        if self.next_token_is(&Token::Semicolon) {
+            self.next_token();
+        }
+    }
+
     fn parse_identifier(&mut self) -> Option<String> {
         match *self.current_token {
             Token::Identifier(ref mut ident) => Some(ident.to_string()),
@@ -157,18 +309,14 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_statement(&mut self) -> Option<ParseItem::Statement> {
-        if *self.next_token == Token::Let
-        // || *self.next_token == Token::Semicolon
-        {
-            self.next_token();
-        }
-        if *self.current_token == Token::Semicolon || *self.current_token == Token::Eof {
-            return None;
-        }
         match *self.current_token {
             Token::Return => self.parse_return_statement(),
             Token::Let => self.parse_let_statement(),
-            Token::Illegal => Some(ParseItem::Statement::None),
+            Token::Struct => self.parse_struct_statement(),
+            // `{}` on its own is an empty block; elsewhere it is an empty map.
+            Token::LeftBrace if self.next_token_is(&Token::RightBrace) => self
+                .parse_block_expression()
+                .map(ParseItem::Statement::Expression),
             _ => self.parse_expression_statement(),
         }
     }
@@ -181,18 +329,29 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_expression(&mut self, order: Order) -> Option<ParseItem::Expression> {
+        let start = self.current_span;
         let mut left = match *self.current_token {
             Token::Identifier(_) => self.parse_identifier_expression(),
             Token::True | Token::False => self.parse_bool_expression(),
             Token::Int(_) => self.parse_int_expression(),
+            Token::Float(_) => self.parse_float_expression(),
+            Token::Str(_) => self.parse_string_expression(),
             Token::LeftBracket => self.parse_array_expression(),
             Token::Fn => self.parse_function_expression(),
-            Token::Exclamation | Token::Minus | Token::Plus => self.parse_prefix_expression(),
-            Token::Semicolon => {
-                self.next_token();
-                return None;
+            Token::Exclamation | Token::Minus | Token::Plus | Token::Tilde => {
+                self.parse_prefix_expression()
+            }
+            Token::Increment | Token::Decrement => self.parse_prefix_update_expression(),
+            Token::LeftParanthesis => self.parse_grouped_expression(),
+            Token::LeftBrace => {
+                if self.next_is_map_literal() {
+                    self.parse_map_expression()
+                } else {
+                    self.parse_block_expression()
+                }
             }
             Token::If => self.parse_if_expression(),
+            Token::Match => self.parse_match_expression(),
             _ => {
                 self.error_no_prefix();
                 return None;
@@ -204,19 +363,39 @@ impl<'a> Parser<'a> {
                 | Token::Minus
                 | Token::Slash
                 | Token::Asterisk
+                | Token::Percent
+                | Token::Power
+                | Token::At
+                | Token::Ampersand
+                | Token::Pipe
+                | Token::Caret
+                | Token::ShiftLeft
+                | Token::ShiftRight
                 | Token::Equal
                 | Token::NotEqual
                 | Token::LessThan
                 | Token::LessThanAndEqual
                 | Token::MoreThan
-                | Token::MoreThanAndEqual
-                | Token::Assign => {
+                | Token::MoreThanAndEqual => {
                     self.next_token();
                     left = self.parse_infix_expression(left.unwrap());
                     // if *self.next_token == Token::Semicolon {
                     //     self.next_token();
                     // }
                 }
+                Token::Assign
+                | Token::PlusAssign
+                | Token::MinusAssign
+                | Token::AsteriskAssign
+                | Token::SlashAssign
+                | Token::PercentAssign => {
+                    self.next_token();
+                    left = self.parse_assign_expression(left.unwrap());
+                }
+                Token::Increment | Token::Decrement => {
+                    self.next_token();
+                    left = self.parse_postfix_expression(left.unwrap());
+                }
                 Token::Identifier(_) => {
                     self.next_token();
                     // self.error_no_prefix();
@@ -227,22 +406,73 @@ impl<'a> Parser<'a> {
                 }
                 Token::LeftParanthesis => {
                     self.next_token();
-                    left = self.parse_call_expression(left.unwrap());
+                    left = self.parse_call_expression(left.unwrap(), start);
+                }
+                Token::Dot => {
+                    self.next_token();
+                    left = self.parse_field_expression(left.unwrap());
                 }
                 _ => return left,
             }
         }
+        left
+    }
 
-        if *self.next_token == Token::Eof
-            && *self.current_token != Token::Eof
-            && *self.current_token != Token::Semicolon
-            && *self.current_token != Token::RightBrace
-        {
-            self.error_next(&Token::Semicolon);
+    fn parse_grouped_expression(&mut self) -> Option<ParseItem::Expression> {
+        self.next_token();
+        let expr = self.parse_expression(Order::Lowest);
+        if !self.expect_next_token(Token::RightParanthesis) {
+            return None;
+        }
+        expr
+    }
+
+    /// With the current token on `{`, tells a map literal (`{}` or
+    /// `{key: ...`) apart from a block. The key may be any expression, so
+    /// this parses one from a copy of the parser and checks for the `:`
+    /// after it. A `{}` statement is handled by `parse_statement` as an
+    /// empty block before this is asked.
+    fn next_is_map_literal(&self) -> bool {
+        if self.next_token_is(&Token::RightBrace) {
+            return true;
+        }
+        let mut lookahead = self.clone();
+        lookahead.next_token();
+        lookahead.parse_expression(Order::Lowest).is_some()
+            && lookahead.next_token_is(&Token::Colon)
+    }
+
+    fn parse_map_expression(&mut self) -> Option<ParseItem::Expression> {
+        let mut pairs = vec![];
+        while !self.next_token_is(&Token::RightBrace) {
+            self.next_token();
+            let key = match self.parse_expression(Order::Lowest) {
+                Some(expr) => expr,
+                None => return None,
+            };
+            if !self.expect_next_token(Token::Colon) {
+                return None;
+            }
             self.next_token();
+            let value = match self.parse_expression(Order::Lowest) {
+                Some(expr) => expr,
+                None => return None,
+            };
+            pairs.push((key, value));
+            if !self.next_token_is(&Token::RightBrace) && !self.expect_next_token(Token::Comma) {
+                return None;
+            }
+        }
+        self.next_token();
+        Some(ParseItem::Expression::Map(pairs))
+    }
+
+    fn parse_block_expression(&mut self) -> Option<ParseItem::Expression> {
+        let body = self.parse_block_statements();
+        if *self.current_token != Token::RightBrace {
             return None;
         }
-        left
+        Some(ParseItem::Expression::Block(body))
     }
 
     fn parse_prefix_expression(&mut self) -> Option<ParseItem::Expression> {
@@ -250,6 +480,7 @@ impl<'a> Parser<'a> {
             Token::Exclamation => ParseItem::Prefix::Not,
             Token::Plus => ParseItem::Prefix::Plus,
             Token::Minus => ParseItem::Prefix::Minus,
+            Token::Tilde => ParseItem::Prefix::BitNot,
             _ => {
                 self.error_no_prefix();
                 return None;
@@ -273,17 +504,29 @@ impl<'a> Parser<'a> {
             Token::Minus => ParseItem::Infix::Minus,
             Token::Asterisk => ParseItem::Infix::Multiply,
             Token::Slash => ParseItem::Infix::Divide,
+            Token::Percent => ParseItem::Infix::Modulo,
+            Token::Power => ParseItem::Infix::Power,
+            Token::At => ParseItem::Infix::MatMul,
+            Token::Ampersand => ParseItem::Infix::BitAnd,
+            Token::Pipe => ParseItem::Infix::BitOr,
+            Token::Caret => ParseItem::Infix::BitXor,
+            Token::ShiftLeft => ParseItem::Infix::ShiftLeft,
+            Token::ShiftRight => ParseItem::Infix::ShiftRight,
             Token::Equal => ParseItem::Infix::Equal,
             Token::NotEqual => ParseItem::Infix::NotEqual,
             Token::LessThanAndEqual => ParseItem::Infix::LessThanAndEqual,
             Token::LessThan => ParseItem::Infix::LessThan,
             Token::MoreThanAndEqual => ParseItem::Infix::MoreThanAndEqual,
             Token::MoreThan => ParseItem::Infix::MoreThan,
-            Token::Assign => ParseItem::Infix::Assign,
             _ => return None,
         };
 
-        let order = self.current_order();
+        // `**` is right-associative: parsing its right-hand side one level
+        // lower lets `2 ** 3 ** 2` group as `2 ** (3 ** 2)`.
+        let order = match infix {
+            ParseItem::Infix::Power => Order::Prefix,
+            _ => self.current_order(),
+        };
 
         self.next_token();
         match self.parse_expression(order) {
@@ -299,53 +542,239 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn parse_index_expression(
+    fn parse_assign_expression(
         &mut self,
-        left: ParseItem::Expression,
+        target: ParseItem::Expression,
     ) -> Option<ParseItem::Expression> {
+        let infix = match *self.current_token {
+            Token::Assign => None,
+            Token::PlusAssign => Some(ParseItem::Infix::Plus),
+            Token::MinusAssign => Some(ParseItem::Infix::Minus),
+            Token::AsteriskAssign => Some(ParseItem::Infix::Multiply),
+            Token::SlashAssign => Some(ParseItem::Infix::Divide),
+            Token::PercentAssign => Some(ParseItem::Infix::Modulo),
+            _ => return None,
+        };
+
+        if infix.is_some() {
+            if !self.check_update_target(&target) {
+                return None;
+            }
+        } else if !target.is_assignable() {
+            self.error_assignment_target(&target);
+            return None;
+        }
+
+        // Assignment is right-associative, so `a = b = 1` assigns both.
         self.next_token();
-        let index = match self.parse_expression(Order::Lowest) {
+        let value = match self.parse_expression(Order::Lowest) {
             Some(expr) => expr,
             None => return None,
         };
 
-        // self.next_token();
+        match infix {
+            Some(infix) => Some(ParseItem::Expression::CompoundAssign(
+                infix,
+                Box::new(target),
+                Box::new(value),
+            )),
+            None => Some(ParseItem::Expression::Infix(
+                ParseItem::Infix::Assign,
+                Box::new(target),
+                Box::new(value),
+            )),
+        }
+    }
 
-        let index2;
-        if *self.next_token != Token::RightBracket {
-            if *self.next_token != Token::Comma {
+    fn parse_prefix_update_expression(&mut self) -> Option<ParseItem::Expression> {
+        let infix = match *self.current_token {
+            Token::Increment => ParseItem::Infix::Plus,
+            Token::Decrement => ParseItem::Infix::Minus,
+            _ => {
                 self.error_no_prefix();
                 return None;
             }
+        };
+
+        self.next_token();
+        let target = match self.parse_expression(Order::Prefix) {
+            Some(expr) => expr,
+            None => return None,
+        };
+
+        if !self.check_update_target(&target) {
+            return None;
+        }
+
+        Some(ParseItem::Expression::CompoundAssign(
+            infix,
+            Box::new(target),
+            Box::new(ParseItem::Expression::Integer(1)),
+        ))
+    }
+
+    fn parse_postfix_expression(
+        &mut self,
+        target: ParseItem::Expression,
+    ) -> Option<ParseItem::Expression> {
+        let postfix = match *self.current_token {
+            Token::Increment => ParseItem::Postfix::Increment,
+            Token::Decrement => ParseItem::Postfix::Decrement,
+            _ => return None,
+        };
+
+        if !self.check_update_target(&target) {
+            return None;
+        }
+
+        Some(ParseItem::Expression::Postfix(postfix, Box::new(target)))
+    }
+
+    fn parse_index_expression(
+        &mut self,
+        left: ParseItem::Expression,
+    ) -> Option<ParseItem::Expression> {
+        if self.next_token_is(&Token::RightBracket) {
             self.next_token();
+            self.error_no_prefix();
+            return None;
+        }
+        let mut indices = vec![];
+        loop {
             self.next_token();
-            match self.parse_expression(Order::Lowest) {
-                Some(expr) => {
-                    index2 = Some(expr);
-                }
+            match self.parse_index() {
+                Some(index) => indices.push(index),
                 None => return None,
-            };
-        } else {
-            index2 = None;
+            }
+            if !self.next_token_is(&Token::Comma) {
+                break;
+            }
+            self.next_token();
         }
         if !self.expect_next_token(Token::RightBracket) {
             return None;
         }
 
-        Some(ParseItem::Expression::Index(
-            Box::new(left),
-            Box::new(index),
-            Box::new(index2),
-        ))
+        Some(ParseItem::Expression::Index(Box::new(left), indices))
+    }
+
+    /// Parses one index of `a[...]`, either an expression or a
+    /// `start:end:step` slice such as `1:3`, `:` or `::2`.
+    fn parse_index(&mut self) -> Option<ParseItem::Expression> {
+        let start = if *self.current_token == Token::Colon {
+            None
+        } else {
+            let expr = match self.parse_expression(Order::Lowest) {
+                Some(expr) => expr,
+                None => return None,
+            };
+            if !self.next_token_is(&Token::Colon) {
+                return Some(expr);
+            }
+            self.next_token();
+            Some(Box::new(expr))
+        };
+        let end = match self.parse_slice_bound() {
+            Some(end) => end,
+            None => return None,
+        };
+        let step = if self.next_token_is(&Token::Colon) {
+            self.next_token();
+            match self.parse_slice_bound() {
+                Some(step) => step,
+                None => return None,
+            }
+        } else {
+            None
+        };
+        Some(ParseItem::Expression::Slice(start, end, step))
+    }
+
+    /// With the current token on a slice's `:`, parses the bound after it
+    /// unless it was left out.
+    fn parse_slice_bound(&mut self) -> Option<Option<Box<ParseItem::Expression>>> {
+        match *self.next_token {
+            Token::Colon | Token::Comma | Token::RightBracket => Some(None),
+            _ => {
+                self.next_token();
+                match self.parse_expression(Order::Lowest) {
+                    Some(expr) => Some(Some(Box::new(expr))),
+                    None => None,
+                }
+            }
+        }
     }
 
     fn parse_identifier_expression(&mut self) -> Option<ParseItem::Expression> {
         match self.parse_identifier() {
-            Some(ident) => Some(ParseItem::Expression::Identifier(ident)),
+            Some(ident) => {
+                if self.next_is_struct_literal() {
+                    self.parse_struct_literal(ident)
+                } else {
+                    Some(ParseItem::Expression::Identifier(ident))
+                }
+            }
             _ => return None,
         }
     }
 
+    /// `Point { x: ...` or `Point {}` after an identifier starts a struct
+    /// literal rather than a block.
+    fn next_is_struct_literal(&self) -> bool {
+        if !self.next_token_is(&Token::LeftBrace) {
+            return false;
+        }
+        let mut lexer = self.lexer.clone();
+        match lexer.next_token() {
+            Token::RightBrace => true,
+            Token::Identifier(_) => lexer.next_token() == Token::Colon,
+            _ => false,
+        }
+    }
+
+    fn parse_struct_literal(&mut self, name: String) -> Option<ParseItem::Expression> {
+        self.next_token();
+        let mut fields = vec![];
+        while !self.next_token_is(&Token::RightBrace) {
+            self.next_token();
+            let field = match self.parse_identifier() {
+                Some(ident) => ident,
+                None => {
+                    self.error_no_prefix();
+                    return None;
+                }
+            };
+            if !self.expect_next_token(Token::Colon) {
+                return None;
+            }
+            self.next_token();
+            let value = match self.parse_expression(Order::Lowest) {
+                Some(expr) => expr,
+                None => return None,
+            };
+            fields.push((field, value));
+            if !self.next_token_is(&Token::RightBrace) && !self.expect_next_token(Token::Comma) {
+                return None;
+            }
+        }
+        self.next_token();
+        Some(ParseItem::Expression::StructLiteral(name, fields))
+    }
+
+    fn parse_field_expression(
+        &mut self,
+        left: ParseItem::Expression,
+    ) -> Option<ParseItem::Expression> {
+        self.next_token();
+        match self.parse_identifier() {
+            Some(field) => Some(ParseItem::Expression::Field(Box::new(left), field)),
+            None => {
+                self.error_no_prefix();
+                None
+            }
+        }
+    }
+
     fn parse_integer_literal(&mut self) -> Option<ParseItem::Expression> {
         match *self.current_token {
             Token::Int(ref mut int) => Some(ParseItem::Expression::Integer(int.clone())),
@@ -353,6 +782,13 @@ impl<'a> Parser<'a> {
         }
     }
 
+    fn parse_string_expression(&mut self) -> Option<ParseItem::Expression> {
+        match *self.current_token {
+            Token::Str(ref string) => Some(ParseItem::Expression::String(string.clone())),
+            _ => None,
+        }
+    }
+
     fn parse_bool_expression(&mut self) -> Option<ParseItem::Expression> {
         match *self.current_token {
             Token::True => Some(ParseItem::Expression::Bool(true)),
@@ -368,6 +804,13 @@ impl<'a> Parser<'a> {
         }
     }
 
+    fn parse_float_expression(&mut self) -> Option<ParseItem::Expression> {
+        match *self.current_token {
+            Token::Float(float) => Some(ParseItem::Expression::Float(float)),
+            _ => None,
+        }
+    }
+
     fn parse_expression_list(&mut self, end: Token) -> Option<Vec<ParseItem::Expression>> {
         let mut vec = vec![];
 
@@ -390,29 +833,49 @@ impl<'a> Parser<'a> {
                 _ => return None,
             }
         }
-        if *self.next_token != end {
+        if !self.expect_next_token(end) {
             return None;
         }
         Some(vec)
     }
 
-    fn parse_array_expression(&mut self) -> Option<ParseItem::Expression> {
+    /// With the current token on `[`, tells the `[{1, 2} {3, 4}]` matrix form
+    /// apart from a list. A list may start with a map literal, `[{"a": 1}]`,
+    /// so a `{` is only a matrix row when no `:` follows its first token.
+    fn next_is_matrix_literal(&self) -> bool {
         if !self.next_token_is(&Token::LeftBrace) {
-            self.error_next(&Token::LeftBrace);
+            return false;
+        }
+        let mut lexer = self.lexer.clone();
+        match lexer.next_token() {
+            Token::RightBrace => true,
+            _ => lexer.next_token() != Token::Colon,
+        }
+    }
+
+    fn parse_list_expression(&mut self) -> Option<ParseItem::Expression> {
+        match self.parse_expression_list(Token::RightBracket) {
+            Some(els) => Some(ParseItem::Expression::List(els)),
+            None => None,
+        }
+    }
+
+    fn parse_array_expression(&mut self) -> Option<ParseItem::Expression> {
+        if !self.next_is_matrix_literal() {
+            return self.parse_list_expression();
         }
         let mut arr: Vec<Vec<ParseItem::Expression>> = vec![];
         while *self.next_token != Token::RightBracket {
-            self.next_token();
-            let vec = self.parse_expression_list(Token::RightBrace);
-            arr.push(vec.unwrap());
-            self.next_token();
-            if *self.current_token != Token::Comma
-                && *self.current_token != Token::RightBrace
-                && *self.current_token != Token::RightBracket
-            {
-                // self.error_no_prefix();
+            if !self.expect_next_token(Token::LeftBrace) {
                 return None;
             }
+            match self.parse_expression_list(Token::RightBrace) {
+                Some(row) => arr.push(row),
+                None => return None,
+            }
+            if self.next_token_is(&Token::Comma) {
+                self.next_token();
+            }
         }
         self.next_token();
         Some(ParseItem::Expression::Array(arr))
@@ -421,6 +884,7 @@ impl<'a> Parser<'a> {
     fn parse_function_parameters(&mut self) -> Option<Vec<String>> {
         let mut params = vec![];
         if *self.next_token == Token::RightParanthesis {
+            self.next_token();
             return Some(params);
         }
         self.next_token();
@@ -479,24 +943,24 @@ impl<'a> Parser<'a> {
         self.next_token();
 
         while *self.current_token != Token::RightBrace && *self.current_token != Token::Eof {
-            match self.parse_statement() {
-                Some(statement) => {
-                    if *self.next_token != Token::Semicolon {
-                        self.error_next(&Token::Semicolon);
-                        *self.current_token = Token::Eof;
-                        *self.next_token = Token::Eof;
-                        self.next_token();
-                        ()
-                    }
-
-                    statements.push(statement);
-                    self.next_token();
-                }
+            match self.parse_terminated_statement(&Token::RightBrace) {
+                Some(statement) => statements.push(statement),
                 None => (),
             }
             self.next_token();
         }
 
+        if *self.current_token != Token::RightBrace {
+            self.err_list.push(ParseError::new(
+                Error::UnexpectedEof,
+                format!(
+                    "expected {}, but found {} instead",
+                    Token::RightBrace,
+                    Token::Eof
+                ),
+            ));
+        }
+
         statements
     }
 
@@ -522,7 +986,6 @@ impl<'a> Parser<'a> {
         }
 
         if !self.expect_next_token(Token::Assign) {
-            self.next_token();
             return None;
         }
 
@@ -531,11 +994,49 @@ impl<'a> Parser<'a> {
             Some(expr) => expr,
             _ => return None,
         };
-        self.next_token();
 
         Some(ParseItem::Statement::Let(ident, eval))
     }
 
+    fn parse_struct_statement(&mut self) -> Option<ParseItem::Statement> {
+        self.next_token();
+        let name = match self.parse_identifier() {
+            Some(ident) => ident,
+            None => {
+                self.error_no_prefix();
+                return None;
+            }
+        };
+        if !self.expect_next_token(Token::LeftBrace) {
+            return None;
+        }
+
+        let mut fields = vec![];
+        while !self.next_token_is(&Token::RightBrace) {
+            self.next_token();
+            match self.parse_identifier() {
+                Some(ident) if fields.contains(&ident) => {
+                    self.err_list.push(ParseError::new(
+                        Error::DuplicateField,
+                        format!("field {} is declared twice in struct {}", ident, name),
+                    ));
+                    return None;
+                }
+                Some(ident) => fields.push(ident),
+                None => {
+                    self.error_no_prefix();
+                    return None;
+                }
+            }
+            if !self.next_token_is(&Token::RightBrace) && !self.expect_next_token(Token::Comma) {
+                return None;
+            }
+        }
+        self.next_token();
+
+        Some(ParseItem::Statement::Struct(name, fields))
+    }
+
     fn parse_return_statement(&mut self) -> Option<ParseItem::Statement> {
         self.next_token();
 
@@ -575,11 +1076,22 @@ impl<'a> Parser<'a> {
         if self.next_token_is(&Token::Else) {
             self.next_token();
 
-            if !self.expect_next_token(Token::LeftBrace) {
-                return None;
-            }
+            if self.next_token_is(&Token::If) {
+                // `else if (...) { ... }` is sugar for an else block holding
+                // just the nested if expression.
+                self.next_token();
+                let alternative = match self.parse_if_expression() {
+                    Some(expr) => expr,
+                    None => return None,
+                };
+                else_body = Some(vec![ParseItem::Statement::Expression(alternative)]);
+            } else {
+                if !self.expect_next_token(Token::LeftBrace) {
+                    return None;
+                }
 
-            else_body = Some(self.parse_block_statements());
+                else_body = Some(self.parse_block_statements());
+            }
         }
 
         Some(ParseItem::Expression::If(
@@ -589,18 +1101,234 @@ impl<'a> Parser<'a> {
         ))
     }
 
+    fn parse_match_expression(&mut self) -> Option<ParseItem::Expression> {
+        if !self.expect_next_token(Token::LeftParanthesis) {
+            return None;
+        }
+
+        self.next_token();
+
+        let scrutinee = match self.parse_expression(Order::Lowest) {
+            Some(expr) => expr,
+            None => return None,
+        };
+
+        if !self.expect_next_token(Token::RightParanthesis)
+            || !self.expect_next_token(Token::LeftBrace)
+        {
+            return None;
+        }
+
+        let mut arms = vec![];
+        while !self.next_token_is(&Token::RightBrace) {
+            if self.next_token_is(&Token::Eof) {
+                self.error_next(&Token::RightBrace);
+                return None;
+            }
+            self.next_token();
+
+            let patterns = match self.parse_patterns() {
+                Some(patterns) => patterns,
+                None => return None,
+            };
+
+            if !self.expect_next_token(Token::FatArrow) {
+                return None;
+            }
+            self.next_token();
+
+            let body = match self.parse_expression(Order::Lowest) {
+                Some(expr) => expr,
+                None => return None,
+            };
+
+            // Like statements, arms whose body is a block need no separator.
+            if self.next_token_is(&Token::Comma) {
+                self.next_token();
+            } else if !self.next_token_is(&Token::RightBrace) {
+                match body {
+                    ParseItem::Expression::Block(_) => (),
+                    _ => {
+                        self.error_next(&Token::Comma);
+                        return None;
+                    }
+                }
+            }
+
+            arms.push(ParseItem::MatchArm { patterns, body });
+        }
+        self.next_token();
+
+        if !Self::is_exhaustive(&arms) {
+            self.err_list.push(ParseError::new(
+                Error::NonExhaustiveMatch,
+                "add a `_` arm to cover the remaining values".to_string(),
+            ));
+            return None;
+        }
+
+        Some(ParseItem::Expression::Match(Box::new(scrutinee), arms))
+    }
+
+    /// A match is exhaustive when one of its arms always matches, or when it
+    /// covers both `true` and `false`. Other values need a wildcard arm.
+    fn is_exhaustive(arms: &[ParseItem::MatchArm]) -> bool {
+        let patterns = arms.iter().flat_map(|arm| arm.patterns.iter());
+        let mut covered_true = false;
+        let mut covered_false = false;
+        for pattern in patterns {
+            match pattern {
+                ParseItem::Pattern::Bool(true) => covered_true = true,
+                ParseItem::Pattern::Bool(false) => covered_false = true,
+                _ if pattern.is_irrefutable() => return true,
+                _ => (),
+            }
+        }
+        covered_true && covered_false
+    }
+
+    fn parse_patterns(&mut self) -> Option<Vec<ParseItem::Pattern>> {
+        let mut patterns = vec![];
+        match self.parse_pattern() {
+            Some(pattern) => patterns.push(pattern),
+            None => return None,
+        }
+        while self.next_token_is(&Token::Pipe) {
+            self.next_token();
+            self.next_token();
+            match self.parse_pattern() {
+                Some(pattern) => patterns.push(pattern),
+                None => return None,
+            }
+        }
+        // The arm body may use any binding, so every alternative must bind
+        // all of them.
+        let first = patterns[0].bindings();
+        for pattern in &patterns[1..] {
+            let bindings = pattern.bindings();
+            if let Some(name) = first.symmetric_difference(&bindings).next() {
+                self.err_list.push(ParseError::new(
+                    Error::MismatchedBindings,
+                    format!(
+                        "{} is not bound by every alternative of {}",
+                        name,
+                        patterns
+                            .iter()
+                            .map(|pattern| pattern.to_string())
+                            .collect::<Vec<String>>()
+                            .join(" | ")
+                    ),
+                ));
+                return None;
+            }
+        }
+        Some(patterns)
+    }
+
+    fn parse_pattern(&mut self) -> Option<ParseItem::Pattern> {
+        match *self.current_token {
+            Token::Int(int) => Some(ParseItem::Pattern::Integer(int)),
+            Token::Minus => match *self.next_token {
+                Token::Int(int) => {
+                    self.next_token();
+                    Some(ParseItem::Pattern::Integer(-int))
+                }
+                _ => {
+                    self.error_next(&Token::Int(0));
+                    None
+                }
+            },
+            Token::True => Some(ParseItem::Pattern::Bool(true)),
+            Token::False => Some(ParseItem::Pattern::Bool(false)),
+            Token::Identifier(ref ident) if ident == "_" => Some(ParseItem::Pattern::Wildcard),
+            Token::Identifier(ref ident) => Some(ParseItem::Pattern::Binding(ident.to_string())),
+            Token::LeftBracket => self.parse_array_pattern(),
+            _ => {
+                self.err_list.push(ParseError::new(
+                    Error::UnexpectedToken,
+                    format!(
+                        "expected a pattern, but found {} instead",
+                        *self.current_token
+                    ),
+                ));
+                None
+            }
+        }
+    }
+
+    fn parse_array_pattern(&mut self) -> Option<ParseItem::Pattern> {
+        if !self.next_token_is(&Token::LeftBrace) {
+            return self.parse_list_pattern();
+        }
+        let mut rows = vec![];
+        while !self.next_token_is(&Token::RightBracket) {
+            if !self.expect_next_token(Token::LeftBrace) {
+                return None;
+            }
+            let mut row = vec![];
+            if !self.next_token_is(&Token::RightBrace) {
+                self.next_token();
+                match self.parse_pattern() {
+                    Some(pattern) => row.push(pattern),
+                    None => return None,
+                }
+                while self.next_token_is(&Token::Comma) {
+                    self.next_token();
+                    self.next_token();
+                    match self.parse_pattern() {
+                        Some(pattern) => row.push(pattern),
+                        None => return None,
+                    }
+                }
+            }
+            if !self.expect_next_token(Token::RightBrace) {
+                return None;
+            }
+            rows.push(row);
+            if self.next_token_is(&Token::Comma) {
+                self.next_token();
+            }
+        }
+        self.next_token();
+        Some(ParseItem::Pattern::Array(rows))
+    }
+
+    fn parse_list_pattern(&mut self) -> Option<ParseItem::Pattern> {
+        let mut patterns = vec![];
+        if !self.next_token_is(&Token::RightBracket) {
+            self.next_token();
+            match self.parse_pattern() {
+                Some(pattern) => patterns.push(pattern),
+                None => return None,
+            }
+            while self.next_token_is(&Token::Comma) {
+                self.next_token();
+                self.next_token();
+                match self.parse_pattern() {
+                    Some(pattern) => patterns.push(pattern),
+                    None => return None,
+                }
+            }
+        }
+        if !self.expect_next_token(Token::RightBracket) {
+            return None;
+        }
+        Some(ParseItem::Pattern::List(patterns))
+    }
+
     pub fn parse_call_expression(
         &mut self,
         expr: ParseItem::Expression,
+        span: Span,
     ) -> Option<ParseItem::Expression> {
         let args = match self.parse_expression_list(Token::RightParanthesis) {
             Some(args) => args,
             None => return None,
         };
-        self.next_token();
         Some(ParseItem::Expression::Call {
             func: Box::new(expr),
             args,
+            span,
         })
     }
 }
@@ -0,0 +1,168 @@
+use crate::error::Error;
+use crate::evaluation_mod::evaluate::Object;
+use crate::interpreter::{parse, Interpreter};
+use crate::parser_mod::ParseItem::{Expression, Statement};
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The outcome of one `test_*` function. A file that fails to parse is
+/// reported once, under the name `<load>`; one that fails while running its
+/// top-level statements fails each of its tests.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestResult {
+    pub file: PathBuf,
+    pub name: String,
+    pub outcome: Result<(), Error>,
+}
+
+impl TestResult {
+    pub fn passed(&self) -> bool {
+        self.outcome.is_ok()
+    }
+}
+
+/// The `.rk` files under `dir`, searched recursively, in path order.
+/// Symbolic links are skipped, so a link back up the tree cannot loop.
+fn script_files(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let io_error = |err: std::io::Error| Error::Io(format!("{}: {}", dir.display(), err));
+    let mut files = vec![];
+    for entry in fs::read_dir(dir).map_err(io_error)? {
+        let path = entry.map_err(io_error)?.path();
+        let file_type = fs::symlink_metadata(&path).map_err(io_error)?.file_type();
+        if file_type.is_symlink() {
+            continue;
+        }
+        if file_type.is_dir() {
+            files.extend(script_files(&path)?);
+        } else if path.extension() == Some(OsStr::new("rk")) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// The `test_*` functions declared at the top level of `source`, in name
+/// order. They are found in the syntax tree, so discovering them runs none
+/// of the file's statements.
+fn test_names(source: &str) -> Result<Vec<String>, Error> {
+    let mut names: Vec<String> = parse(source)?
+        .into_iter()
+        .filter_map(|statement| match statement {
+            Statement::Expression(Expression::Function(name, ..)) if name.starts_with("test_") => {
+                Some(name)
+            }
+            _ => None,
+        })
+        .collect();
+    names.sort();
+    names.dedup();
+    Ok(names)
+}
+
+/// A fresh interpreter with `source` loaded. Tests may read fixtures next to
+/// their file, so its directory is allowed for the file builtins.
+fn load(file: &Path, source: &str) -> Result<Interpreter, Error> {
+    let mut interpreter = Interpreter::new();
+    if let Some(dir) = file.parent() {
+        interpreter.allow_dir(dir)?;
+    }
+    interpreter.eval_str(source)?;
+    Ok(interpreter)
+}
+
+/// Runs every `test_*` function defined in the `.rk` files under `dir`.
+/// Each test gets its own interpreter, with its file evaluated anew, so
+/// globals changed by one test are never seen by another.
+pub fn run_tests<P: AsRef<Path>>(dir: P) -> Result<Vec<TestResult>, Error> {
+    let mut results = vec![];
+    for file in script_files(dir.as_ref())? {
+        let source = fs::read_to_string(&file)
+            .map_err(|err| Error::Io(format!("{}: {}", file.display(), err)))?;
+        let names = match test_names(&source) {
+            Ok(names) => names,
+            Err(err) => {
+                results.push(TestResult {
+                    file,
+                    name: "<load>".to_string(),
+                    outcome: Err(err),
+                });
+                continue;
+            }
+        };
+        for name in names {
+            let outcome = load(&file, &source)
+                .and_then(|mut interpreter| interpreter.eval_str(&format!("{}();", name)))
+                .map(|_: Object| ());
+            results.push(TestResult {
+                file: file.clone(),
+                name,
+                outcome,
+            });
+        }
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_tests() {
+        let dir = std::env::temp_dir().join(format!("rakiu-tests-{}", std::process::id()));
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::write(
+            dir.join("matrix.rk"),
+            "fn test_identity() {\n  let m = [{1, 2} {3, 4}];\n  assert_eq(identity(2) @ m, m);\n}\n\
+             fn test_det() {\n  assert_eq(det([{1, 2} {3, 4}]), 2);\n}\n\
+             fn sq(x) { x * x }\n\
+             fn test_helper() {\n  assert_eq(sq(3), 9);\n}\n",
+        )
+        .unwrap();
+        fs::write(dir.join("nested/broken.rk"), "let x = ;").unwrap();
+        // Each load appends an `x`, so the file shows how often it ran.
+        let runs = dir.join("nested/runs.txt");
+        fs::write(&runs, "").unwrap();
+        fs::write(
+            dir.join("nested/counter.rk"),
+            format!(
+                "let runs = \"{}\";\nwrite_file(runs, format(\"{{}}x\", read_file(runs)));\nfn test_once() {{ 1 }}\n",
+                runs.display()
+            ),
+        )
+        .unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&dir, dir.join("nested/loop")).unwrap();
+        fs::write(
+            dir.join("notes.txt"),
+            "fn test_ignored() { assert(false); }",
+        )
+        .unwrap();
+
+        let results = run_tests(&dir).unwrap();
+        let summary: Vec<(String, bool)> = results
+            .iter()
+            .map(|result| (result.name.clone(), result.passed()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("test_det".to_string(), false),
+                ("test_helper".to_string(), true),
+                ("test_identity".to_string(), true),
+                ("<load>".to_string(), false),
+                ("test_once".to_string(), true),
+            ]
+        );
+        assert_eq!(fs::read_to_string(&runs).unwrap(), "x");
+        assert_eq!(
+            results[0].outcome,
+            Err(Error::Runtime(
+                "Assertion failed at line 6, column 3: -2 != 2".to_string()
+            ))
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}